@@ -0,0 +1,40 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Static site generator and server for markdown content.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Configuration file.
+    #[arg(short, long, global = true, default_value = "config.yml")]
+    pub config: PathBuf,
+    /// Folder with the content, overriding the configuration.
+    #[arg(short, long, global = true)]
+    pub source: Option<String>,
+    /// Folder for the generated site, overriding the configuration.
+    #[arg(short, long, global = true)]
+    pub destination: Option<String>,
+    /// Port of the server.
+    #[arg(short, long, global = true, env = "PORT", default_value_t = 8080)]
+    pub port: u16,
+    /// Address the server listens on.
+    #[arg(short, long, global = true, env = "BIND", default_value = "0.0.0.0")]
+    pub bind: String,
+    /// What to do. Watches the content by default.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub enum Command {
+    /// Generate the site once and exit.
+    Build,
+    /// Serve the site already generated, without generating it.
+    Serve,
+    /// Generate the site, serve it and regenerate it as the content changes.
+    #[command(alias = "dev")]
+    Watch,
+    /// Validate the whole site without publishing it, exiting with an error
+    /// if anything is wrong.
+    Check,
+}
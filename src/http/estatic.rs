@@ -1,18 +1,19 @@
 use axum::{
     extract::{Path, State},
     routing::get,
-    response::Html,
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
     Router
 };
-use std::path::PathBuf;
+use std::path::{Component, PathBuf};
 use tower_http::services::ServeDir;
 use tracing::{debug, error};
 use crate::models::create_page_error;
+use crate::utils::PUBLISHING;
 
 use super::super::models::Config;
 
-pub async fn router() -> Router {
-    let config = Config::read_configuration().await;
+pub fn router(config: Config) -> Router {
     let path = config.destination.clone();
     
     debug!("Serving from: {}", &path);
@@ -23,14 +24,31 @@ pub async fn router() -> Router {
         .with_state(config)
 }
 
-async fn get_root(state: State<Config>) -> Html<String>{
+async fn get_root(state: State<Config>) -> Response{
     debug!("=== root ===");
     get_index(state, Path("".to_string())).await
 }
-async fn get_index(State(config): State<Config>, Path(path): Path<String>) -> Html<String>{
+async fn get_index(State(config): State<Config>, Path(path): Path<String>) -> Response{
     debug!("=== directory: {} ===", config.destination);
     debug!("Destination: {:?}", &config.destination);
     debug!("Path: {:?}", &path);
+    if !is_relative(&path) {
+        error!("Error. path {:?} is outside the site", &path);
+        return create_page_error(404, "Page not found", &config.site).into_response();
+    }
+    // held until the response is read, so the site is not swapped meanwhile
+    let published = PUBLISHING.read().await;
+    if let Some(redirect) = published.find(&path) {
+        debug!("Redirecting {} to {}", path, redirect.to);
+        let status = StatusCode::from_u16(redirect.status).unwrap_or(StatusCode::MOVED_PERMANENTLY);
+        return (status, [(header::LOCATION, redirect.to.clone())]).into_response();
+    }
+    let file_path = PathBuf::new()
+        .join(&config.destination)
+        .join(&path);
+    if let Some(content_type) = content_type(&file_path) {
+        return get_file(&config, &file_path, content_type).await;
+    }
     let index_path = PathBuf::new()
         .join(&config.destination)
         .join(&path)
@@ -39,15 +57,46 @@ async fn get_index(State(config): State<Config>, Path(path): Path<String>) -> Ht
     if let Ok(true) = tokio::fs::try_exists(&index_path).await {
         match tokio::fs::read_to_string(&index_path).await{
             Ok(content) => {
-                Html(content)
+                Html(content).into_response()
             },
             Err(e) => {
                 error!("Error: {}", e);
-                create_page_error(500, &e.to_string(), &config.site)
+                create_page_error(500, &e.to_string(), &config.site).into_response()
             },
         }
     }else{
         error!("Error. directory {:?} not exists", &index_path);
-        create_page_error(404, "Page not found", &config.site)
+        create_page_error(404, "Page not found", &config.site).into_response()
+    }
+}
+
+/// Serve a generated file that is not a page, like the feeds. The caller
+/// holds `PUBLISHING`.
+async fn get_file(config: &Config, file_path: &PathBuf, content_type: &'static str) -> Response{
+    debug!("File path: {:?}", file_path);
+    match tokio::fs::read(file_path).await {
+        Ok(content) => ([(header::CONTENT_TYPE, content_type)], content).into_response(),
+        Err(e) => {
+            error!("Error. file {:?} not available: {}", file_path, e);
+            create_page_error(404, "Page not found", &config.site).into_response()
+        },
+    }
+}
+
+/// Whether the already decoded `path` stays inside the destination folder,
+/// having only plain segments, and no `..`, root or prefix.
+fn is_relative(path: &str) -> bool {
+    std::path::Path::new(path).components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+fn content_type(path: &std::path::Path) -> Option<&'static str> {
+    match path.extension()?.to_str()? {
+        "html" => Some("text/html; charset=utf-8"),
+        "xml" => Some("application/xml; charset=utf-8"),
+        "json" => Some("application/json"),
+        "txt" => Some("text/plain; charset=utf-8"),
+        "css" => Some("text/css; charset=utf-8"),
+        _ => None,
     }
 }
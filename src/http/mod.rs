@@ -9,20 +9,22 @@ use tower_http::{
     trace::TraceLayer,
 };
 use core::error;
-use std::env::var;
+use super::models::{redirect, Config};
+use super::utils::PUBLISHING;
 use tracing::debug;
 use tower::{Layer, ServiceBuilder};
 
-pub async fn serve() -> Result<(), Box<dyn error::Error>>{
-    let port: u16 = var("PORT").ok().and_then(|port| port.parse().ok()).unwrap_or(8080);
+/// Serve the generated site of `config` on `bind`:`port`.
+pub async fn serve(config: Config, bind: &str, port: u16) -> Result<(), Box<dyn error::Error>>{
     debug!("Starting server on port: {}", port);
-    let router = estatic::router().await.layer(
+    *PUBLISHING.write().await = redirect::load(std::path::Path::new(&config.destination)).await;
+    let router = estatic::router(config).layer(
         ServiceBuilder::new()
             // Enables logging. Use `RUST_LOG=tower_http=debug`
             .layer(TraceLayer::new_for_http())
     );
     let app = NormalizePathLayer::trim_trailing_slash().layer(router);
-    let addr = format!("0.0.0.0:{}", port);
+    let addr = format!("{}:{}", bind, port);
     let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, ServiceExt::<Request>::into_make_service(app))
         .await
@@ -1,3 +1,4 @@
+mod cli;
 mod models;
 mod http;
 mod utils;
@@ -10,14 +11,20 @@ use tracing_subscriber::{
 use std::path::Path;
 use notify::{Event, RecursiveMode, Result, Watcher};
 use std::sync::mpsc;
+use std::time::Duration;
 use std::str::FromStr;
 use std::env::var;
-use tracing::{debug, error};
+use std::process;
+use tracing::{debug, error, info};
 use utils::Generator;
+use clap::Parser;
+use cli::{Cli, Command};
 use models::Config;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+const DEBOUNCE: u64 = 100;
+const SCHEDULER_CHECK: u64 = 60;
 
 #[tokio::main]
 async fn main(){
@@ -27,23 +34,58 @@ async fn main(){
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let config = Arc::new(Mutex::new(Config::read_configuration().await));
+    let cli = Cli::parse();
+    let mut config = Config::read_configuration(&cli.config).await;
+    if let Some(source) = cli.source {
+        config.source = source;
+    }
+    if let Some(destination) = cli.destination {
+        config.destination = destination;
+    }
+    let config = Arc::new(Mutex::new(config));
 
-    let binding = Arc::clone(&config);
-    let replicator = Generator::new(&binding).await;
-    replicator.initial_replication().await;
+    match cli.command.unwrap_or(Command::Watch) {
+        Command::Build => {
+            let replicator = Generator::new(&config).await;
+            let report = replicator.initial_replication().await;
+            process::exit(if report.is_empty() { 0 } else { 1 });
+        },
+        Command::Serve => {
+            let config = config.lock().await.clone();
+            server(config, cli.bind, cli.port).await;
+        },
+        Command::Watch => {
+            let replicator = Generator::new(&config).await;
+            replicator.initial_replication().await;
 
+            debug!("Starting server");
+            tokio::spawn(server(config.lock().await.clone(), cli.bind, cli.port));
+            tokio::spawn(scheduler(replicator.clone()));
+            monitor(replicator, Arc::clone(&config)).await;
+        },
+        Command::Check => {
+            let replicator = Generator::new(&config).await;
+            process::exit(check(replicator).await);
+        },
+    }
+}
 
-    debug!("Starting server");
-    tokio::spawn(async {
-        server().await;
-    });
-    monitor(Arc::clone(&config)).await;
+/// Validate the whole site without publishing it, returning the exit code.
+async fn check(replicator: Generator) -> i32 {
+    let report = replicator.check().await;
+    if report.is_empty() {
+        info!("The site has no errors");
+        0
+    } else {
+        report.log();
+        error!("The site has {} errors", report.len());
+        1
+    }
 }
 
-async fn server(){
+async fn server(config: Config, bind: String, port: u16){
     debug!("Starting server");
-    match http::serve().await {
+    match http::serve(config, &bind, port).await {
         Ok(()) => debug!("Server started"),
         Err(err) => {
             error!("Can start server: {}", err);
@@ -56,11 +98,31 @@ async fn server(){
     }
 }
 
-async fn monitor(mutex_config: Arc<Mutex<Config>>){
+/// Wake up when the next scheduled page has to be published, or when a page
+/// is scheduled before it, and at least every `SCHEDULER_CHECK` seconds.
+async fn scheduler(replicator: Generator){
+    debug!("Starting scheduler");
+    loop {
+        let mut wait = Duration::from_secs(SCHEDULER_CHECK);
+        if let Some(next) = replicator.next_scheduled().await {
+            debug!("Next scheduled page at {}", next);
+            if let Ok(until) = (next - chrono::Utc::now()).to_std() {
+                wait = wait.min(until);
+            } else {
+                wait = Duration::ZERO;
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => replicator.publish_scheduled().await.log(),
+            _ = replicator.rescheduled() => debug!("A page was scheduled earlier"),
+        }
+    }
+}
+
+async fn monitor(replicator: Generator, mutex_config: Arc<Mutex<Config>>){
     debug!("Starting monitor");
-    let config = mutex_config.lock().await;
+    let config = mutex_config.lock().await.clone();
     debug!("Config: {:?}", config);
-    let replicator = Generator::new(&mutex_config).await;
     //replicator.initial_replication().await;
     let (tx, rx) = mpsc::channel::<Result<Event>>();
 
@@ -74,24 +136,31 @@ async fn monitor(mutex_config: Arc<Mutex<Config>>){
     // below will be monitored for changes.
     debug!("Watching: {}", config.source);
     watcher.watch(Path::new(&config.source), RecursiveMode::Recursive).unwrap();
-    // Block forever, printing out events as they come in
-    for res in rx {
-        match res {
-            Ok(event) => {
-                match replicator.replicate(event).await {
-                    Ok(_) => {},
-                    Err(err) => {
-                        error!("Can not generate {} from {}. Error: {}", config.destination, config.source, err);
-                        let mut err = err.as_ref();
-                        while let Some(next_err) = err.source() {
-                            error!("caused by: {:#}", next_err);
-                            err = next_err;
-                        }
-
+    // Block forever, replicating events as they come in. Editors usually save
+    // with a burst of create/modify/rename events, so duplicated events that
+    // arrive together are only replicated once.
+    while let Ok(res) = rx.recv() {
+        let mut batch = vec![res];
+        while let Ok(next) = rx.recv_timeout(Duration::from_millis(DEBOUNCE)) {
+            batch.push(next);
+        }
+        let mut seen = Vec::new();
+        for res in batch {
+            match res {
+                Ok(event) => {
+                    let key = (event.kind, event.paths.clone());
+                    if seen.contains(&key) {
+                        continue;
                     }
-                }
-            },
-            Err(e) => println!("watch error: {:?}", e),
+                    seen.push(key);
+                    let report = replicator.replicate(event).await;
+                    if !report.is_empty() {
+                        error!("Can not generate {} from {}. {} errors", config.destination, config.source, report.len());
+                        report.log();
+                    }
+                },
+                Err(e) => println!("watch error: {:?}", e),
+            }
         }
     }
 }
@@ -0,0 +1,208 @@
+use serde::{Serialize, Deserialize};
+use chrono::{Datelike, TimeZone};
+use chrono_tz::Tz;
+use minijinja::context;
+use tracing::error;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use super::{render, BuildError, Page, Site};
+
+const TEMPLATE: &str = "archive.html";
+
+/// Published pages grouped by year and month.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Year {
+    pub year: i32,
+    pub url: String,
+    pub count: usize,
+    pub months: Vec<Month>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Month {
+    pub year: i32,
+    pub month: u32,
+    /// First day of the month, to format it with the `date` filter.
+    pub date: String,
+    pub url: String,
+    pub count: usize,
+    pub pages: Vec<Page>,
+}
+
+/// Group `pages` by the year and month of their date in the site timezone,
+/// newest first.
+pub fn group(site: &Site, pages: &[Page]) -> Vec<Year> {
+    let tz: Tz = site.timezone.parse().unwrap_or_else(|e| {
+        error!("Invalid timezone {}: {}", site.timezone, e);
+        Tz::UTC
+    });
+    let mut groups: BTreeMap<Reverse<(i32, u32)>, Vec<Page>> = BTreeMap::new();
+    for page in pages.iter().filter(|page| !page.draft) {
+        let date = page.metadata.date.with_timezone(&tz);
+        groups.entry(Reverse((date.year(), date.month())))
+            .or_default()
+            .push(page.clone());
+    }
+    let mut years: Vec<Year> = Vec::new();
+    for (Reverse((year, month)), mut pages) in groups {
+        pages.sort_by_key(|page| Reverse(page.metadata.date));
+        // the first hour of the month that exists, as midnight can fall in
+        // a daylight saving gap
+        let date = (0..24)
+            .find_map(|hour| tz.with_ymd_and_hms(year, month, 1, hour, 0, 0).earliest())
+            .map(|date| date.to_rfc3339())
+            .unwrap_or_default();
+        let month = Month {
+            year,
+            month,
+            date,
+            url: url(site, &Path::new(&year.to_string()).join(format!("{:02}", month))),
+            count: pages.len(),
+            pages,
+        };
+        match years.last_mut() {
+            Some(last) if last.year == year => {
+                last.count += month.count;
+                last.months.push(month);
+            },
+            _ => years.push(Year {
+                year,
+                url: url(site, Path::new(&year.to_string())),
+                count: month.count,
+                months: vec![month],
+            }),
+        }
+    }
+    years
+}
+
+/// Render `archive/`, `archive/<year>/` and `archive/<year>/<month>/`
+/// into `destination`, returning the generated files. The template gets
+/// the `years` shown in every page, and the `year` and `month` it is
+/// about, if any.
+pub async fn generate(site: &Site, destination: &Path, pages: &[Page]) -> Vec<Result<PathBuf, BuildError>> {
+    let folder = destination.join("archive");
+    let years = group(site, pages);
+    let mut generated = Vec::new();
+    let ctx = context!(
+        site => site,
+        years => years,
+    );
+    generated.push(render(TEMPLATE, ctx, &folder.join("index.html")).await);
+    for year in years.iter() {
+        let year_folder = folder.join(year.year.to_string());
+        let ctx = context!(
+            site => site,
+            years => vec![year],
+            year => year,
+        );
+        generated.push(render(TEMPLATE, ctx, &year_folder.join("index.html")).await);
+        for month in year.months.iter() {
+            let ctx = context!(
+                site => site,
+                years => vec![Year { months: vec![month.clone()], count: month.count, ..year.clone() }],
+                year => year,
+                month => month,
+            );
+            let destination_file = year_folder.join(format!("{:02}", month.month)).join("index.html");
+            generated.push(render(TEMPLATE, ctx, &destination_file).await);
+        }
+    }
+    generated
+}
+
+fn url(site: &Site, route: &Path) -> String {
+    PathBuf::from(&site.url)
+        .join("archive")
+        .join(route)
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn site(timezone: &str) -> Site {
+        serde_json::from_value(json!({
+            "url": "https://example.com/",
+            "language": "en",
+            "language_direction": "ltr",
+            "theme": "light",
+            "title": "Site",
+            "description": "",
+            "author": "",
+            "avatar": "",
+            "email": "",
+            "timezone": timezone,
+        })).unwrap()
+    }
+
+    fn page(title: &str, date: &str, draft: bool) -> Page {
+        serde_json::from_value(json!({
+            "route": "blog",
+            "metadata": {
+                "title": title,
+                "date": date,
+                "publicated": !draft,
+                "template": "page.html",
+            },
+            "content": "",
+            "draft": draft,
+        })).unwrap()
+    }
+
+    fn titles(month: &Month) -> Vec<&str> {
+        month.pages.iter().map(|page| page.metadata.title.as_str()).collect()
+    }
+
+    #[test]
+    fn pages_are_grouped_newest_first() {
+        let pages = [
+            page("a", "2023-11-20T10:00:00Z", false),
+            page("b", "2024-02-01T10:00:00Z", false),
+            page("c", "2023-11-25T10:00:00Z", false),
+            page("d", "2024-01-15T10:00:00Z", false),
+        ];
+        let years = group(&site("UTC"), &pages);
+        assert_eq!(years.iter().map(|year| (year.year, year.count)).collect::<Vec<_>>(), vec![(2024, 2), (2023, 2)]);
+        assert_eq!(years[0].months.iter().map(|month| month.month).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(titles(&years[1].months[0]), vec!["c", "a"]);
+        assert_eq!(years[1].months[0].url, "https://example.com/archive/2023/11");
+    }
+
+    #[test]
+    fn drafts_are_left_out() {
+        let pages = [
+            page("a", "2024-01-15T10:00:00Z", false),
+            page("b", "2024-01-16T10:00:00Z", true),
+        ];
+        let years = group(&site("UTC"), &pages);
+        assert_eq!(titles(&years[0].months[0]), vec!["a"]);
+    }
+
+    #[test]
+    fn months_are_the_ones_of_the_site_timezone() {
+        let pages = [page("a", "2024-01-01T02:00:00Z", false)];
+        let years = group(&site("America/New_York"), &pages);
+        assert_eq!((years[0].year, years[0].months[0].month), (2023, 12));
+        assert_eq!(years[0].months[0].date, "2023-12-01T00:00:00-05:00");
+    }
+
+    #[test]
+    fn month_starting_in_a_daylight_saving_gap_has_a_date() {
+        // clocks in Asunción went from 00:00 to 01:00 on 2023-10-01
+        let pages = [page("a", "2023-10-10T12:00:00-03:00", false)];
+        let years = group(&site("America/Asuncion"), &pages);
+        assert_eq!(years[0].months[0].date, "2023-10-01T01:00:00-03:00");
+    }
+
+    #[test]
+    fn invalid_timezone_falls_back_to_utc() {
+        let pages = [page("a", "2024-01-01T02:00:00+05:00", false)];
+        let years = group(&site("Nowhere/Else"), &pages);
+        assert_eq!((years[0].year, years[0].months[0].month), (2023, 12));
+    }
+}
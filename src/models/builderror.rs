@@ -0,0 +1,119 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use super::{Format, FrontMatterError};
+
+/// Something that went wrong while building the site. It always carries the
+/// file it is about: the source of a page, or the generated file for the
+/// listings that have no source.
+#[derive(Debug)]
+pub enum BuildError {
+    /// A source can not be read.
+    Read { path: PathBuf, cause: io::Error },
+    /// A generated file, or its folder, can not be written.
+    Write { path: PathBuf, cause: io::Error },
+    /// The front matter of a page can not be parsed.
+    FrontMatter { path: PathBuf, cause: FrontMatterError },
+    /// A page does not start with a front matter.
+    MissingFrontMatter { path: PathBuf },
+    /// The front matter does not fit the `Metadata` of a page, with the
+    /// line and column of the wrong value when it is in the front matter.
+    Metadata { path: PathBuf, format: Format, location: Option<(usize, usize)>, cause: serde_path_to_error::Error<serde_json::Error> },
+    /// The metadata lacks a required value.
+    Invalid { path: PathBuf, message: String },
+    /// The template does not exist or can not be compiled.
+    Template { path: PathBuf, template: String, cause: minijinja::Error },
+    /// The template fails while rendering.
+    Render { path: PathBuf, template: String, cause: minijinja::Error },
+    /// A redirect, from the aliases of a page or from the redirects file,
+    /// is discarded.
+    Redirect { path: PathBuf, message: String },
+    /// Two sources generate the same file, like two pages with the same
+    /// slug, so one of them is lost.
+    Duplicate { path: PathBuf, output: PathBuf, other: PathBuf },
+    /// A link to a page or file of the site that does not exist.
+    BrokenLink { path: PathBuf, link: String },
+    /// The highlighting theme does not exist.
+    Theme { path: PathBuf, theme: String },
+}
+
+impl BuildError {
+    pub fn path(&self) -> &Path {
+        match self {
+            BuildError::Read { path, .. }
+            | BuildError::Write { path, .. }
+            | BuildError::FrontMatter { path, .. }
+            | BuildError::MissingFrontMatter { path }
+            | BuildError::Metadata { path, .. }
+            | BuildError::Invalid { path, .. }
+            | BuildError::Template { path, .. }
+            | BuildError::Render { path, .. }
+            | BuildError::Redirect { path, .. }
+            | BuildError::Duplicate { path, .. }
+            | BuildError::BrokenLink { path, .. }
+            | BuildError::Theme { path, .. } => path,
+        }
+    }
+
+    /// Line of the file where the error is, when it is known.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            BuildError::FrontMatter { cause, .. } => Some(cause.line),
+            BuildError::Metadata { location, .. } => location.map(|(line, _)| line),
+            _ => None,
+        }
+    }
+
+    /// Column of the line where the error is, when it is known.
+    pub fn column(&self) -> Option<usize> {
+        match self {
+            BuildError::FrontMatter { cause, .. } => cause.column,
+            BuildError::Metadata { location, .. } => location.map(|(_, column)| column),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path().display())?;
+        if let Some(line) = self.line() {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column() {
+                write!(f, ":{}", column)?;
+            }
+        }
+        match self {
+            BuildError::Read { cause, .. } => write!(f, ": Can not read. {}", cause),
+            BuildError::Write { cause, .. } => write!(f, ": Can not write. {}", cause),
+            BuildError::FrontMatter { cause, .. } => write!(f, ": Invalid {} front matter. {}", cause.format, cause.message),
+            BuildError::MissingFrontMatter { .. } => write!(f, ": There is no front matter"),
+            BuildError::Metadata { format, cause, .. } => write!(f, ": Invalid {} metadata. {}", format, cause),
+            BuildError::Invalid { message, .. } => write!(f, ": Invalid metadata. {}", message),
+            BuildError::Template { template, cause, .. } => write!(f, ": Can not get template {}. {}", template, cause),
+            BuildError::Render { template, cause, .. } => write!(f, ": Can not render {}. {}", template, cause),
+            BuildError::Redirect { message, .. } => write!(f, ": {}", message),
+            BuildError::Duplicate { output, other, .. } => write!(f, ": Generates {}, like {}", output.display(), other.display()),
+            BuildError::BrokenLink { link, .. } => write!(f, ": Broken link to {}", link),
+            BuildError::Theme { theme, .. } => write!(f, ": There is no highlighting theme {}", theme),
+        }
+    }
+}
+
+impl Error for BuildError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BuildError::Read { cause, .. } | BuildError::Write { cause, .. } => Some(cause),
+            BuildError::FrontMatter { cause, .. } => Some(cause),
+            BuildError::Metadata { cause, .. } => Some(cause),
+            BuildError::Template { cause, .. } | BuildError::Render { cause, .. } => Some(cause),
+            BuildError::MissingFrontMatter { .. }
+            | BuildError::Invalid { .. }
+            | BuildError::Redirect { .. }
+            | BuildError::Duplicate { .. }
+            | BuildError::BrokenLink { .. }
+            | BuildError::Theme { .. } => None,
+        }
+    }
+}
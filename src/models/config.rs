@@ -1,6 +1,6 @@
 use serde::{Serialize, Deserialize};
 use tokio::fs::read_to_string;
-use std::{process, fmt::{self, Display}};
+use std::{env::var, path::Path, process, fmt::{self, Display}};
 use super::Site;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -9,6 +9,21 @@ pub struct Config{
     pub destination: String,
     pub assets: String,
     pub site: Site,
+    /// Generate unpublished pages too, for previewing them locally. Can be
+    /// overridden with the `DRAFTS` environment variable.
+    #[serde(default)]
+    pub drafts: bool,
+    /// Generate pages dated in the future instead of scheduling them. Can be
+    /// overridden with the `FUTURE` environment variable.
+    #[serde(default)]
+    pub future: bool,
+    /// Pages per page of the indexes. 0 lists all of them in one page.
+    #[serde(default)]
+    pub paginate: usize,
+    /// YAML file with a list of site-wide redirects (`from`, `to` and
+    /// optionally `status`).
+    #[serde(default)]
+    pub redirects: Option<String>,
 }
 
 impl Display for Config{
@@ -22,20 +37,29 @@ impl Display for Config{
 }
 
 impl Config {
-    pub async fn read_configuration() -> Config{
-        let content = match read_to_string("config.yml")
+    /// Read the configuration from `path`, exiting if it is not valid.
+    pub async fn read_configuration(path: &Path) -> Config{
+        let content = match read_to_string(path)
             .await {
                 Ok(value) => value,
                 Err(e) => {
-                    println!("Error with config file `config.yml`: {e}");
-                    process::exit(0);
+                    println!("Error with config file `{}`: {e}", path.display());
+                    process::exit(1);
                 }
             };
-        match serde_yaml::from_str(&content){
-            Ok(configuration) => configuration,
+        match serde_yaml::from_str::<Config>(&content){
+            Ok(mut configuration) => {
+                if let Some(drafts) = var("DRAFTS").ok().and_then(|value| value.parse().ok()) {
+                    configuration.drafts = drafts;
+                }
+                if let Some(future) = var("FUTURE").ok().and_then(|value| value.parse().ok()) {
+                    configuration.future = future;
+                }
+                configuration
+            },
             Err(e) => {
-                println!("Error with config file `config.yml`: {e}");
-                process::exit(0);
+                println!("Error with config file `{}`: {e}", path.display());
+                process::exit(1);
             }
         }
     }
@@ -0,0 +1,86 @@
+use serde_json::{Map, Value};
+use super::{BuildError, Format, FrontMatter, FrontMatterError};
+use std::path::Path;
+
+/// Name of the file with the front matter defaults of a folder.
+pub const DEFAULTS_FILE: &str = "_defaults.yml";
+
+/// Front matter values that the pages of a folder inherit, from the
+/// `_defaults.yml` of the folder and of every folder above it, the deeper
+/// ones overriding the rest.
+#[derive(Debug, Default, Clone)]
+pub struct Defaults {
+    pub values: Map<String, Value>,
+    /// Permalink pattern of the folder, from the front matter of its
+    /// `index.md`.
+    pub permalink: Option<String>,
+}
+
+impl Defaults {
+    /// Defaults of `folder`, being `origin` the root of the source. Fails
+    /// when any of the files can not be parsed or is not a map.
+    pub async fn read(origin: &Path, folder: &Path) -> Result<Self, BuildError> {
+        let mut folders: Vec<&Path> = folder.ancestors()
+            .take_while(|ancestor| ancestor.starts_with(origin))
+            .collect();
+        folders.reverse();
+        let mut values = Value::Object(Map::new());
+        for ancestor in folders {
+            let path = ancestor.join(DEFAULTS_FILE);
+            let Ok(content) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            match serde_yaml::from_str::<Value>(&content) {
+                Ok(Value::Object(defaults)) => merge(&mut values, Value::Object(defaults)),
+                Ok(Value::Null) => {},
+                Ok(_) => return Err(BuildError::Invalid { path, message: "The defaults have to be a map".to_string() }),
+                Err(e) => return Err(BuildError::FrontMatter {
+                    path,
+                    cause: FrontMatterError {
+                        format: Format::Yaml,
+                        line: e.location().map(|l| l.line()).unwrap_or(1),
+                        column: e.location().map(|l| l.column()),
+                        message: e.to_string(),
+                    },
+                }),
+            }
+        }
+        let values = match values {
+            Value::Object(values) => values,
+            _ => Map::new(),
+        };
+        Ok(Self {
+            values,
+            permalink: section_permalink(folder).await,
+        })
+    }
+
+    /// Front matter of a page with the defaults it does not set.
+    pub fn apply(&self, front_matter: Value) -> Value {
+        let mut values = Value::Object(self.values.clone());
+        merge(&mut values, front_matter);
+        values
+    }
+}
+
+/// Merge `overlay` into `base`, recursively for maps.
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        },
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Permalink pattern for the pages of `folder`, from its `index.md`.
+async fn section_permalink(folder: &Path) -> Option<String> {
+    let data = tokio::fs::read_to_string(folder.join("index.md")).await.ok()?;
+    FrontMatter::parse(&data).ok()??
+        .data
+        .get("permalink")?
+        .as_str()
+        .map(str::to_string)
+}
@@ -0,0 +1,176 @@
+use serde::{Serialize, Deserialize};
+use chrono::{DateTime, FixedOffset, Utc};
+use minijinja::context;
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+use super::{render, BuildError, Episode, Page, Podcast, Site};
+
+/// Feeds settings of the site.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Feeds {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Whether the entries carry the whole content or only the excerpt.
+    #[serde(default)]
+    pub content: FeedContent,
+    /// Maximum number of entries of every feed.
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedContent {
+    Full,
+    #[default]
+    Excerpt,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_limit() -> usize {
+    20
+}
+
+impl Default for Feeds {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            content: FeedContent::default(),
+            limit: default_limit(),
+        }
+    }
+}
+
+/// Addresses of the feeds of a listing, used for `<link rel="alternate">`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedLinks {
+    pub title: String,
+    pub rss: String,
+    pub atom: String,
+}
+
+impl FeedLinks {
+    pub fn new(site: &Site, title: &str, route: &Path) -> Self {
+        Self {
+            title: title.to_string(),
+            rss: absolute_url(&site.url, &route.join("rss.xml")),
+            atom: absolute_url(&site.url, &route.join("atom.xml")),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Entry {
+    pub title: String,
+    pub link: String,
+    pub date: DateTime<FixedOffset>,
+    pub author: String,
+    /// Content or excerpt, depending on the feed settings.
+    pub content: String,
+    pub tags: Vec<String>,
+    /// Audio of the entry, with absolute urls.
+    pub episode: Option<Episode>,
+    pub transcript_type: Option<String>,
+}
+
+/// RSS 2.0 and Atom feeds for a listing of pages.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Feed {
+    pub title: String,
+    pub description: String,
+    /// Address of the listing the feed belongs to.
+    pub link: String,
+    pub links: FeedLinks,
+    pub updated: DateTime<Utc>,
+    pub entries: Vec<Entry>,
+    /// Podcast of the feed, with absolute urls, for `podcast.xml`.
+    pub podcast: Option<Podcast>,
+}
+
+impl Feed {
+    /// Feed with the latest published `pages`, being `route` the folder of
+    /// the listing.
+    pub fn new(site: &Site, title: &str, description: &str, route: &Path, pages: &[Page]) -> Self {
+        Self::build(site, title, description, route, pages.iter().collect(), site.feeds.limit)
+    }
+
+    /// Feed with every published episode in `pages`.
+    pub fn podcast(site: &Site, podcast: &Podcast, route: &Path, pages: &[Page]) -> Self {
+        let episodes = pages.iter()
+            .filter(|page| page.metadata.episode.is_some())
+            .collect();
+        Self {
+            podcast: Some(podcast.absolute(&site.url)),
+            ..Self::build(site, &podcast.title, &podcast.description, route, episodes, usize::MAX)
+        }
+    }
+
+    fn build(site: &Site, title: &str, description: &str, route: &Path, pages: Vec<&Page>, limit: usize) -> Self {
+        let mut pages: Vec<&Page> = pages.into_iter()
+            .filter(|page| !page.draft)
+            .collect();
+        pages.sort_by_key(|page| Reverse(page.metadata.date));
+        pages.truncate(limit);
+        let entries = pages.iter()
+            .map(|page| Entry {
+                title: page.metadata.title.clone(),
+                link: page.url.clone(),
+                date: page.metadata.date,
+                author: site.author.clone(),
+                content: match site.feeds.content {
+                    FeedContent::Full => page.content.clone(),
+                    FeedContent::Excerpt if page.summary.is_empty() => page.metadata.excerpt.clone(),
+                    FeedContent::Excerpt => page.summary.clone(),
+                },
+                tags: page.metadata.tags.clone(),
+                episode: page.metadata.episode.as_ref().map(|episode| episode.absolute(&site.url)),
+                transcript_type: page.metadata.episode.as_ref()
+                    .and_then(Episode::transcript_type)
+                    .map(str::to_string),
+            })
+            .collect();
+        Self {
+            title: title.to_string(),
+            description: description.to_string(),
+            link: absolute_url(&site.url, route),
+            links: FeedLinks::new(site, title, route),
+            updated: Utc::now(),
+            entries,
+            podcast: None,
+        }
+    }
+
+    /// Render `rss.xml` and `atom.xml` into `folder`, returning the
+    /// generated files.
+    pub async fn generate(&self, site: &Site, folder: &Path) -> Vec<Result<PathBuf, BuildError>> {
+        let mut generated = Vec::new();
+        for name in ["rss.xml", "atom.xml"] {
+            generated.push(self.render(site, name, folder).await);
+        }
+        generated
+    }
+
+    /// Render the `feeds/<name>` template into `folder/<name>`.
+    pub async fn render(&self, site: &Site, name: &str, folder: &Path) -> Result<PathBuf, BuildError> {
+        let ctx = context!(
+            site => site,
+            feed => self,
+        );
+        render(&format!("feeds/{}", name), ctx, &folder.join(name)).await
+    }
+}
+
+/// Join `path` to the site url. The url of the site has to be absolute
+/// (e.g. `https://example.com/`) for feeds readers to follow the links.
+pub fn absolute_url(base: &str, path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        format!("{}/", base.trim_end_matches('/'))
+    } else {
+        format!("{}/{}", base.trim_end_matches('/'), path)
+    }
+}
@@ -0,0 +1,298 @@
+use serde_json::{Map, Number, Value};
+use serde_path_to_error::{Path, Segment};
+use std::error::Error;
+use std::fmt;
+
+/// Format of the front matter of a page, given by its delimiter: `---` for
+/// YAML, `+++` for TOML, as in Hugo and Zola, and a `{ ... }` object for
+/// JSON.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Yaml => write!(f, "YAML"),
+            Format::Toml => write!(f, "TOML"),
+            Format::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+/// A front matter that can not be parsed, with the line of the file where
+/// it failed.
+#[derive(Debug)]
+pub struct FrontMatterError {
+    pub format: Format,
+    pub line: usize,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for FrontMatterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid {} front matter at line {}. {}", self.format, self.line, self.message)
+    }
+}
+
+impl Error for FrontMatterError {}
+
+/// A page split into its front matter and its markdown content.
+#[derive(Debug, Clone)]
+pub struct FrontMatter {
+    pub format: Format,
+    pub data: Value,
+    pub content: String,
+    /// The front matter as written, with the lines it has in the file.
+    pub text: String,
+}
+
+impl FrontMatter {
+    /// Split `text` into its front matter and its content. Returns `None`
+    /// when the text does not start with a front matter.
+    pub fn parse(text: &str) -> Result<Option<Self>, FrontMatterError> {
+        let text = text.trim_start_matches('\u{feff}');
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let Some(first) = lines.first().map(|line| line.trim_end()) else {
+            return Ok(None);
+        };
+        let (format, start, end, rest) = match first {
+            "---" | "+++" => {
+                let format = if first == "---" { Format::Yaml } else { Format::Toml };
+                let Some(close) = lines.iter().skip(1).position(|line| line.trim_end() == first) else {
+                    return Err(FrontMatterError {
+                        format,
+                        line: 1,
+                        column: None,
+                        message: format!("There is no closing {}", first),
+                    });
+                };
+                (format, 1, close + 1, close + 2)
+            },
+            _ if first.starts_with('{') => return parse_json(text).map(Some),
+            _ => return Ok(None),
+        };
+        // the opening delimiter is kept as a blank line, so the parsers
+        // report the lines of the file
+        let source = "\n".repeat(start) + &lines[start..end].concat();
+        let error = |location: Option<(usize, usize)>, message: String| FrontMatterError {
+            format,
+            line: location.map(|(line, _)| line).unwrap_or(1),
+            column: location.map(|(_, column)| column),
+            message,
+        };
+        let data = if format == Format::Yaml {
+            serde_yaml::from_str::<Value>(&source)
+                .map_err(|e| error(e.location().map(|l| (l.line(), l.column())), e.to_string()))?
+        } else {
+            toml::from_str::<toml::Table>(&source)
+                .map(|table| toml_to_json(toml::Value::Table(table)))
+                .map_err(|e| {
+                    let message = e.message().trim().lines().collect::<Vec<&str>>().join(". ");
+                    error(e.span().map(|span| line_column(&source, span.start)), message)
+                })?
+        };
+        Ok(Some(Self {
+            format,
+            data,
+            content: lines[rest.min(lines.len())..].concat(),
+            text: source,
+        }))
+    }
+
+    /// Line and column of the front matter where the value of `path` is
+    /// written, as reported by `serde_path_to_error`. It is `None` when the
+    /// value is not there, like the ones from the defaults of the folder.
+    pub fn locate(&self, path: &Path) -> Option<(usize, usize)> {
+        let mut offset = 0;
+        let mut location = None;
+        for segment in path.iter() {
+            let Segment::Map { key } = segment else {
+                continue;
+            };
+            let start = find_key(&self.text, offset, key)?;
+            location = Some(line_column(&self.text, start));
+            offset = start + key.len();
+        }
+        location
+    }
+}
+
+/// Byte offset of the first `key` after `offset` written as a key: a YAML
+/// `key:`, a TOML `key =` or `[table.key]`, or a JSON `"key":`.
+fn find_key(text: &str, offset: usize, key: &str) -> Option<usize> {
+    text[offset..].match_indices(key)
+        .map(|(index, _)| offset + index)
+        .find(|&start| {
+            let before = text[..start].chars().next_back();
+            let after = &text[start + key.len()..];
+            // a quoted key ends with its quote, unlike `"date: ..."` in a value
+            let after = match before.filter(|c| *c == '"' || *c == '\'') {
+                Some(quote) => match after.strip_prefix(quote) {
+                    Some(after) => after,
+                    None => return false,
+                },
+                None => after,
+            }.trim_start();
+            before.is_none_or(|c| c.is_whitespace() || "\"'{,[.-".contains(c))
+                && after.starts_with([':', '=', ']', '.'])
+        })
+}
+
+/// JSON front matter is the first object of the text, that ends where the
+/// object does, in any line.
+fn parse_json(text: &str) -> Result<FrontMatter, FrontMatterError> {
+    let mut values = serde_json::Deserializer::from_str(text).into_iter::<Value>();
+    let data = match values.next() {
+        Some(Ok(data)) => data,
+        Some(Err(e)) => return Err(FrontMatterError {
+            format: Format::Json,
+            line: e.line().max(1),
+            column: Some(e.column().max(1)),
+            message: e.to_string(),
+        }),
+        None => return Err(FrontMatterError {
+            format: Format::Json,
+            line: 1,
+            column: None,
+            message: "There is no front matter".to_string(),
+        }),
+    };
+    let end = values.byte_offset();
+    let rest = &text[end..];
+    // the content starts in the line after the closing brace
+    let content = match rest.split_once('\n') {
+        Some((line, content)) if line.trim().is_empty() => content,
+        Some(_) => rest,
+        None => rest.trim_start(),
+    };
+    Ok(FrontMatter {
+        format: Format::Json,
+        data,
+        content: content.to_string(),
+        text: text[..end].to_string(),
+    })
+}
+
+/// Line and column, both starting at 1, of the byte `offset` of `text`.
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map(|last| last.chars().count()).unwrap_or(0) + 1;
+    (line, column)
+}
+
+/// TOML dates are turned into strings, like the ones of the other formats.
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Number(i.into()),
+        toml::Value::Float(f) => Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(a) => Value::Array(a.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(t) => Value::Object(t.into_iter()
+            .map(|(k, v)| (k, toml_to_json(v)))
+            .collect::<Map<String, Value>>()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// Maps all the way down, so a `null` fails at the path that leads to it.
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Nested(HashMap<String, Nested>);
+
+    fn locate(text: &str, keys: &[&str]) -> Option<(usize, usize)> {
+        let front_matter = FrontMatter::parse(text).unwrap().unwrap();
+        let mut value = json!(null);
+        for key in keys.iter().rev() {
+            value = json!({ *key: value });
+        }
+        let path = serde_path_to_error::deserialize::<_, Nested>(value).unwrap_err().path().clone();
+        front_matter.locate(&path)
+    }
+
+    #[test]
+    fn yaml_and_toml_are_split_from_the_content() {
+        let yaml = FrontMatter::parse("---\ntitle: Hello\n---\nContent\n").unwrap().unwrap();
+        assert_eq!(yaml.format, Format::Yaml);
+        assert_eq!(yaml.data, json!({ "title": "Hello" }));
+        assert_eq!(yaml.content, "Content\n");
+        let toml = FrontMatter::parse("+++\ntitle = \"Hello\"\n+++\nContent\n").unwrap().unwrap();
+        assert_eq!(toml.format, Format::Toml);
+        assert_eq!(toml.data, json!({ "title": "Hello" }));
+        assert_eq!(toml.content, "Content\n");
+    }
+
+    #[test]
+    fn text_without_front_matter_is_none() {
+        assert!(FrontMatter::parse("# Hello\n").unwrap().is_none());
+        assert!(FrontMatter::parse("").unwrap().is_none());
+    }
+
+    #[test]
+    fn unclosed_front_matter_is_an_error() {
+        let error = FrontMatter::parse("---\ntitle: Hello\n").unwrap_err();
+        assert_eq!(error.line, 1);
+    }
+
+    #[test]
+    fn errors_have_the_line_of_the_file() {
+        let yaml = FrontMatter::parse("---\ntitle: Hello\ntags: [a\n---\n").unwrap_err();
+        assert_eq!(yaml.line, 4);
+        let toml = FrontMatter::parse("+++\ntitle = \"Hello\"\ntags = \n+++\n").unwrap_err();
+        assert_eq!((toml.line, toml.column), (3, Some(8)));
+        let json = FrontMatter::parse("{\n\"title\": \"Hello\",\n\"tags\": [1,,2]\n}\n").unwrap_err();
+        assert_eq!((json.line, json.column), (3, Some(12)));
+    }
+
+    #[test]
+    fn toml_dates_become_strings() {
+        let toml = FrontMatter::parse("+++\ndate = 2024-01-02T03:04:05Z\n+++\n").unwrap().unwrap();
+        assert_eq!(toml.data, json!({ "date": "2024-01-02T03:04:05Z" }));
+    }
+
+    #[test]
+    fn json_ends_where_its_object_does() {
+        let one_line = FrontMatter::parse("{\"title\": \"Hello\"}\nContent\n").unwrap().unwrap();
+        assert_eq!(one_line.format, Format::Json);
+        assert_eq!(one_line.data, json!({ "title": "Hello" }));
+        assert_eq!(one_line.content, "Content\n");
+        let nested = FrontMatter::parse("{\n  \"toc\": {\n    \"show\": true\n  }\n}\nContent\n").unwrap().unwrap();
+        assert_eq!(nested.data, json!({ "toc": { "show": true } }));
+        assert_eq!(nested.content, "Content\n");
+        let same_line = FrontMatter::parse("{\"title\": \"}\"} Content\n").unwrap().unwrap();
+        assert_eq!(same_line.data, json!({ "title": "}" }));
+        assert_eq!(same_line.content, " Content\n");
+    }
+
+    #[test]
+    fn byte_order_mark_is_skipped() {
+        let yaml = FrontMatter::parse("\u{feff}---\ntitle: Hello\n---\n").unwrap().unwrap();
+        assert_eq!(yaml.data, json!({ "title": "Hello" }));
+    }
+
+    #[test]
+    fn keys_are_located_in_every_format() {
+        assert_eq!(locate("---\ntitle: \"date: x\"\ndate: [1]\n---\n", &["date"]), Some((3, 1)));
+        assert_eq!(locate("+++\ntitle = \"B\"\n\n[toc]\ndepth = \"x\"\n+++\n", &["toc", "depth"]), Some((5, 1)));
+        assert_eq!(locate("+++\ntoc.depth = \"x\"\n+++\n", &["toc", "depth"]), Some((2, 5)));
+        assert_eq!(locate("{\"title\": \"C\", \"toc\": {\"depth\": -3}}\n", &["toc", "depth"]), Some((1, 25)));
+    }
+
+    #[test]
+    fn keys_from_elsewhere_are_not_located() {
+        assert_eq!(locate("---\ntitle: Hello\n---\n", &["template"]), None);
+    }
+}
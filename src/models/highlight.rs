@@ -0,0 +1,183 @@
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, line_tokens_to_classed_spans, ClassStyle};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxSet};
+use syntect::util::LinesWithEndings;
+use tracing::debug;
+use super::BuildError;
+
+static SYNTAXES: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static THEMES: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
+
+/// Classes of the highlighted tokens start with it, so they do not clash
+/// with the ones of the site.
+const PREFIX: &str = "hl-";
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: PREFIX };
+
+/// Stylesheet with the colors of the theme, generated in the root of the
+/// site.
+pub const STYLESHEET: &str = "highlight.css";
+
+/// Styles of the line numbers, highlighted lines and captions, that do not
+/// depend on the theme.
+const LINES_CSS: &str = "
+.hl-code .lineno { display: inline-block; min-width: 2em; margin-right: 1em; text-align: right; opacity: 0.5; user-select: none; }
+.hl-code .highlighted { display: inline-block; width: 100%; background-color: rgba(127, 127, 127, 0.25); }
+.code-block figcaption { font-family: monospace; font-size: 0.9em; }
+";
+
+/// Syntax highlighting of the fenced code blocks.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Highlight {
+    pub enabled: bool,
+    /// Theme of the stylesheet, one of `base16-ocean.dark`,
+    /// `base16-eighties.dark`, `base16-mocha.dark`, `base16-ocean.light`,
+    /// `InspiredGitHub`, `Solarized (dark)` and `Solarized (light)`.
+    pub theme: String,
+}
+
+impl Default for Highlight {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            theme: "base16-ocean.dark".to_string(),
+        }
+    }
+}
+
+/// What the info string of a fenced code block asks for, like
+/// `rust,linenos,hl_lines=1 3-5,name=main.rs`: the language first, and
+/// then, separated by commas, `linenos` to number the lines, `linenostart`
+/// for the first number, `hl_lines` with the lines or ranges of lines to
+/// highlight and `name` for a caption.
+#[derive(Debug, Default, PartialEq)]
+struct Info {
+    lang: String,
+    linenos: bool,
+    linenostart: usize,
+    hl_lines: Vec<(usize, usize)>,
+    name: Option<String>,
+}
+
+impl Info {
+    fn parse(info: &str) -> Self {
+        let mut parts = info.split(',').map(str::trim);
+        let mut parsed = Self {
+            lang: parts.next().unwrap_or_default().to_string(),
+            linenostart: 1,
+            ..Self::default()
+        };
+        for part in parts {
+            let (key, value) = part.split_once('=').map(|(key, value)| (key.trim(), value.trim())).unwrap_or((part, ""));
+            match key {
+                "linenos" => parsed.linenos = true,
+                "linenostart" => parsed.linenostart = value.parse().unwrap_or(1),
+                "hl_lines" => parsed.hl_lines = value.split_whitespace()
+                    .filter_map(|range| match range.split_once('-') {
+                        Some((start, end)) => Some((start.parse().ok()?, end.parse().ok()?)),
+                        None => range.parse().ok().map(|line| (line, line)),
+                    })
+                    .collect(),
+                "name" | "title" => parsed.name = Some(value.trim_matches('"').to_string()),
+                _ => debug!("Unknown option of code block: {}", part),
+            }
+        }
+        parsed
+    }
+
+    fn is_highlighted(&self, line: usize) -> bool {
+        self.hl_lines.iter().any(|(start, end)| (*start..=*end).contains(&line))
+    }
+}
+
+/// Html of a fenced code block with its tokens classed by syntax. Languages
+/// that are not known are left as plain text.
+pub fn highlight(code: &str, info: &str) -> String {
+    let info = Info::parse(info);
+    let syntax = SYNTAXES.find_syntax_by_token(&info.lang)
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text());
+    let mut state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    let mut html = String::new();
+    if let Some(name) = &info.name {
+        let _ = write!(html, "<figure class=\"code-block\"><figcaption>{}</figcaption>", escape(name));
+    }
+    let _ = write!(html, "<pre class=\"{}code\"", PREFIX);
+    if !info.lang.is_empty() {
+        let _ = write!(html, " data-lang=\"{}\"><code class=\"language-{}\">", escape(&info.lang), escape(&info.lang));
+    } else {
+        html.push_str("><code>");
+    }
+    for (number, line) in LinesWithEndings::from(code).enumerate() {
+        let number = number + 1;
+        // every line closes its spans and opens again the ones that go on
+        // in the next one, so it can be wrapped on its own
+        let open: Vec<String> = stack.as_slice().iter()
+            .map(|scope| scope.build_string().split('.')
+                .map(|atom| format!("{}{}", PREFIX, atom))
+                .collect::<Vec<String>>()
+                .join(" "))
+            .collect();
+        let tokens = state.parse_line(line, &SYNTAXES)
+            .ok()
+            .and_then(|ops| line_tokens_to_classed_spans(line, &ops, CLASS_STYLE, &mut stack).ok());
+        let mut content = match tokens {
+            Some((content, _)) => content,
+            None => escape(line),
+        };
+        if let Some(newline) = content.rfind('\n') {
+            content.remove(newline);
+        }
+        let class = if info.is_highlighted(number) { "line highlighted" } else { "line" };
+        let _ = write!(html, "<span class=\"{}\">", class);
+        if info.linenos {
+            let _ = write!(html, "<span class=\"lineno\">{}</span>", number + info.linenostart - 1);
+        }
+        for classes in open.iter() {
+            let _ = write!(html, "<span class=\"{}\">", classes);
+        }
+        html.push_str(&content);
+        for _ in 0..stack.len() {
+            html.push_str("</span>");
+        }
+        html.push_str("</span>\n");
+    }
+    html.push_str("</code></pre>");
+    if info.name.is_some() {
+        html.push_str("</figure>");
+    }
+    html.push('\n');
+    html
+}
+
+/// Write the stylesheet of the theme into `destination/highlight.css`,
+/// when highlighting is enabled.
+pub async fn generate(highlight: &Highlight, destination: &Path) -> Vec<Result<PathBuf, BuildError>> {
+    if !highlight.enabled {
+        return Vec::new();
+    }
+    let destination_file = destination.join(STYLESHEET);
+    let css = THEMES.themes.get(&highlight.theme)
+        .and_then(|theme| css_for_theme_with_class_style(theme, CLASS_STYLE).ok());
+    let Some(css) = css else {
+        return vec![Err(BuildError::Theme { path: destination_file, theme: highlight.theme.clone() })];
+    };
+    match tokio::fs::write(&destination_file, css + LINES_CSS).await {
+        Ok(()) => {
+            debug!("Generated {:?}", destination_file);
+            vec![Ok(destination_file)]
+        },
+        Err(cause) => vec![Err(BuildError::Write { path: destination_file, cause })],
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,29 +1,56 @@
 use serde::{Serialize, Deserialize};
-use gray_matter::{engine::YAML, Matter};
-use tracing::{
-    error,
-    debug
-};
+use tracing::debug;
 use std::path::PathBuf;
 use std::collections::HashMap;
-use std::error::Error;
 use minijinja::context;
-use comrak::{markdown_to_html, Options};
+use super::page::{normalize_permalink, site_url};
 use super::{
+    BuildError,
     ENV,
+    FeedLinks,
+    FrontMatter,
+    Heading,
     Metadata,
+    Order,
     Page,
+    Paginator,
     Site,
+    SortBy,
+    Toc,
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Index{
     pub index: Page,
     pub pages: Vec<Page>,
+    /// Indexes of the subfolders.
+    #[serde(default)]
+    pub sections: Vec<Section>,
+}
+
+/// A subfolder of an index, as exposed to templates.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Section {
+    pub index: Page,
+    pub url: String,
+    /// Number of pages in the subfolder and below.
+    pub count: usize,
+}
+
+impl Section {
+    pub fn new(index: Page, count: usize) -> Self {
+        Self {
+            url: index.url.clone(),
+            index,
+            count,
+        }
+    }
 }
 
 impl Index {
-    pub async fn read(route: &PathBuf, source_folder: &PathBuf, destination_folder: &PathBuf, pages: Vec<Page>) -> Result<Self, Box<dyn Error>> {
+    pub async fn read(site: &Site, route: &PathBuf, source_folder: &PathBuf, destination_folder: &PathBuf, pages: Vec<Page>) -> Result<Self, BuildError> {
+        let permalink = normalize_permalink(&route.to_string_lossy())
+            .map_err(|message| BuildError::Invalid { path: source_folder.clone(), message })?;
         debug!("Route: {:?}", route);
         debug!("Source folder: {:?}", source_folder);
         debug!("Destination folder: {:?}", destination_folder);
@@ -33,28 +60,43 @@ impl Index {
         debug!("Reading index: {:?}", &source);
         if let Ok(true) = tokio::fs::try_exists(&source).await {
             debug!("File exists: {:?}", &source);
-            let data = tokio::fs::read_to_string(&source).await?;
+            let data = tokio::fs::read_to_string(&source).await
+                .map_err(|cause| BuildError::Read { path: source.clone(), cause })?;
             debug!("Data: {}", data);
-            let matter = Matter::<YAML>::new();
-            let result = matter.parse(&data);
+            let result = FrontMatter::parse(&data)
+                .map_err(|cause| BuildError::FrontMatter { path: source.clone(), cause })?
+                .ok_or_else(|| BuildError::MissingFrontMatter { path: source.clone() })?;
             debug!("Result: {:?}", result);
-            let mut metadata: Metadata = result
-                .data
-                .ok_or("Can not read metadata")?
-                .deserialize()?;
-            metadata.init();
-            metadata.validate()?;
+            let mut metadata: Metadata = serde_path_to_error::deserialize(result.data.clone())
+                .map_err(|cause| BuildError::Metadata { path: source.clone(), format: result.format, location: result.locate(cause.path()), cause })?;
+            let markdown = site.markdown.with(metadata.markdown.as_ref())
+                .map_err(|e| BuildError::Invalid { path: source.clone(), message: format!("Invalid markdown options. {}", e) })?;
+            let rendered = markdown.render(&result.content);
+            metadata.init(&rendered.description(site.description_words));
+            metadata.validate()
+                .map_err(|e| BuildError::Invalid { path: source.clone(), message: e.to_string() })?;
+            let pages = sort_pages(pages, &metadata);
             Ok(Self {
                 index: Page {
                     route: route.to_path_buf(),
+                    source,
+                    draft: !metadata.publicated,
+                    tags: Vec::new(),
+                    taxonomies: HashMap::new(),
+                    permalink: permalink.clone(),
+                    url: site_url(site, &permalink),
+                    toc: Heading::tree(&rendered.headings, metadata.toc.depth),
+                    summary: rendered.summary,
+                    description: metadata.excerpt.clone(),
                     metadata,
-                    content: markdown_to_html(&result.content, &Options::default()),
+                    content: rendered.content,
                 },
                 pages,
+                sections: Vec::new(),
             })
         }else{
             debug!("File does not exist: {:?}", &source);
-            let slug = destination_folder.file_name().unwrap().to_str().unwrap().to_string();
+            let slug = destination_folder.file_name().unwrap_or_default().to_string_lossy().to_string();
             let title = slug.replace("-", " ");
             let metadata = Metadata {
                 title: title.clone(),
@@ -65,62 +107,131 @@ impl Index {
                 tags: Vec::new(),
                 publicated: true,
                 template: "index.html".to_string(),
+                weight: 0,
+                pinned: false,
+                sort_by: SortBy::default(),
+                order: None,
+                paginate: None,
+                markdown: None,
+                toc: Toc::default(),
+                episode: None,
+                recursive: false,
+                permalink: None,
+                url: None,
+                aliases: Vec::new(),
+                extra: HashMap::new(),
             };
-            metadata.validate()?;
+            metadata.validate()
+                .map_err(|e| BuildError::Invalid { path: source_folder.clone(), message: e.to_string() })?;
+            let pages = sort_pages(pages, &metadata);
             Ok(Self {
                 index: Page {
                     route: route.to_path_buf(),
+                    source: source_folder.clone(),
                     metadata,
                     content: "".to_string(),
+                    toc: Vec::new(),
+                    summary: String::new(),
+                    description: String::new(),
+                    draft: false,
+                    tags: Vec::new(),
+                    taxonomies: HashMap::new(),
+                    permalink: permalink.clone(),
+                    url: site_url(site, &permalink),
                 },
                 pages,
+                sections: Vec::new(),
             })
         }
     }
 
-    pub async fn generate(&self, site: &Site, parent: &PathBuf) {
+    /// List every page below the index instead of only its own ones.
+    pub fn include_descendants(&mut self, pages: Vec<Page>) {
+        self.pages = sort_pages(pages, &self.index.metadata);
+    }
+
+    /// Render the index into `parent/index.html`, and the rest of its pages
+    /// into `parent/page/<number>/index.html` when it is paginated,
+    /// returning the generated files. `paginate` is the site default for
+    /// the number of pages per page, that the index can override.
+    pub async fn generate(&self, site: &Site, parent: &PathBuf, paginate: usize) -> Vec<Result<PathBuf, BuildError>> {
         debug!("--- Start generation {:?} - {:?}", &parent, &self.index.route);
         debug!("Parent: {:?}", parent);
-        debug!("Generate {:?}index.html", parent);
         debug!("Route: {:?}", self.index.route);
-        let destination_file = PathBuf::new()
-            .join(parent)
-            .join("index.html");
-        debug!("Destination: {:?}", self.index.route);
-        match tokio::fs::create_dir_all(&parent).await {
-            Ok(_) => debug!("Created parent directory: {:?}", &parent),
-            Err(e) => error!("Can not create parent directory: {:?}. {}", &parent, e),
-        }
-        if let Ok(true) = tokio::fs::try_exists(&destination_file).await {
-            debug!("File exists. Overwriting {:?}", &destination_file);
-            match tokio::fs::remove_file(&destination_file).await {
-                Ok(_) => debug!("Removed file: {:?}", &destination_file),
-                Err(e) => error!("Can not remove file: {:?}. {}", &destination_file, e),
+        let per_page = self.index.metadata.paginate.unwrap_or(paginate);
+        let base_url = self.index.url.clone();
+        let mut generated = Vec::new();
+        let template_name = &self.index.metadata.template;
+        let template = match ENV.get_template(template_name) {
+            Ok(template) => template,
+            Err(cause) => {
+                generated.push(Err(BuildError::Template {
+                    path: self.index.source.clone(),
+                    template: template_name.clone(),
+                    cause,
+                }));
+                return generated;
+            },
+        };
+        debug!("Template: {:?}", template);
+        // the feeds of the root folder are the ones of the whole site
+        let feed = (site.feeds.enabled && self.index.route.as_os_str() != "")
+            .then(|| FeedLinks::new(site, &self.index.metadata.title, &self.index.route));
+        for paginator in Paginator::paginate(&self.pages, per_page, &base_url) {
+            let destination_folder = parent.join(paginator.folder());
+            let destination_file = destination_folder.join("index.html");
+            debug!("Generate {:?}", destination_file);
+            if let Err(cause) = tokio::fs::create_dir_all(&destination_folder).await {
+                generated.push(Err(BuildError::Write { path: destination_folder, cause }));
+                continue;
             }
-        }
-        debug!("Pages: {:?}", self.pages);
-        let ctx = context!(
-            site => site,
-            page => self.index,
-            pages => self.pages,
-        );
-        debug!("Context: {:?}", ctx);
-        match ENV.get_template(&self.index.metadata.template) {
-            Ok(template) => {
-                debug!("Template: {:?}", template);
-                match template.render(&ctx) {
-                    Ok(rendered) => {
-                        match tokio::fs::write(&destination_file, &rendered).await {
-                            Ok(_) => debug!("Generated index: {:?}", &destination_file),
-                            Err(e) => error!("Can not generate index: {:?}. {}", &destination_file, e),
-                        }
+            let ctx = context!(
+                site => site,
+                page => self.index,
+                pages => paginator.pages,
+                sections => self.sections,
+                paginator => paginator,
+                feed => feed,
+            );
+            debug!("Context: {:?}", ctx);
+            let result = match template.render(&ctx) {
+                Ok(rendered) => match tokio::fs::write(&destination_file, &rendered).await {
+                    Ok(_) => {
+                        debug!("Generated index: {:?}", &destination_file);
+                        Ok(destination_file)
                     },
-                    Err(e) => error!("Can not render template. {}", e),
-                }
-            },
-            Err(e) => error!("Can not get template. {}", e),
-
+                    Err(cause) => Err(BuildError::Write { path: destination_file, cause }),
+                },
+                Err(cause) => Err(BuildError::Render {
+                    path: self.index.source.clone(),
+                    template: template_name.clone(),
+                    cause,
+                }),
+            };
+            generated.push(result);
         }
         debug!("--- End generation {:?} - {:?}", &parent, &self.index.route);
+        generated
     }
 }
+
+/// Sort pages as requested by the index metadata, with pinned pages first.
+fn sort_pages(mut pages: Vec<Page>, index: &Metadata) -> Vec<Page> {
+    let order = index.order.unwrap_or(match index.sort_by {
+        SortBy::Date => Order::Desc,
+        SortBy::Title | SortBy::Weight => Order::Asc,
+    });
+    pages.sort_by(|a, b| {
+        let ordering = match index.sort_by {
+            SortBy::Date => a.metadata.date.cmp(&b.metadata.date),
+            SortBy::Title => a.metadata.title.to_lowercase().cmp(&b.metadata.title.to_lowercase()),
+            SortBy::Weight => a.metadata.weight.cmp(&b.metadata.weight),
+        };
+        let ordering = match order {
+            Order::Asc => ordering,
+            Order::Desc => ordering.reverse(),
+        };
+        b.metadata.pinned.cmp(&a.metadata.pinned).then(ordering)
+    });
+    pages
+}
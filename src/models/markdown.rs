@@ -0,0 +1,237 @@
+use comrak::adapters::HeadingAdapter;
+use comrak::{format_html_with_plugins, parse_document, Arena, Options, Plugins};
+use comrak::nodes::{AstNode, NodeValue};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use tracing::error;
+use super::defaults::merge;
+use super::highlight::{self, Highlight};
+use super::toc::{Collector, Heading, Headings};
+
+/// Options of the markdown renderer, from the `markdown` section of the
+/// site. Pages can override any of them, but `highlight`, with a `markdown`
+/// map with the same sections in their front matter. Every option is off by
+/// default.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Markdown {
+    pub extension: Extension,
+    pub parse: Parse,
+    pub render: Render,
+    /// Only for the whole site, as there is one stylesheet for every page.
+    pub highlight: Highlight,
+    pub headings: Headings,
+}
+
+/// Html of a markdown content, with its headings and its summary.
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    pub content: String,
+    /// Every heading of the content, in the order they appear.
+    pub headings: Vec<Heading>,
+    /// Html of the content before the `<!-- more -->` separator, or else of
+    /// its first paragraph.
+    pub summary: String,
+    /// Plain text of the summary, or of the whole content when it has no
+    /// summary.
+    pub text: String,
+}
+
+impl Rendered {
+    /// The first `words` words of the plain text.
+    pub fn description(&self, words: usize) -> String {
+        let mut text: Vec<&str> = self.text.split_whitespace().collect();
+        if text.len() > words {
+            text.truncate(words);
+            format!("{}…", text.join(" "))
+        } else {
+            text.join(" ")
+        }
+    }
+}
+
+/// Syntax extensions to CommonMark.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Extension {
+    pub strikethrough: bool,
+    /// Escape the html tags that GitHub filters, like `<script>`.
+    pub tagfilter: bool,
+    pub table: bool,
+    pub autolink: bool,
+    pub tasklist: bool,
+    pub superscript: bool,
+    pub subscript: bool,
+    pub footnotes: bool,
+    pub description_lists: bool,
+    pub multiline_block_quotes: bool,
+    pub math_dollars: bool,
+    pub math_code: bool,
+    pub underline: bool,
+    pub spoiler: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Parse {
+    /// Turn quotes, dashes and ellipses into their typographic versions.
+    pub smart: bool,
+    /// Language of the fenced code blocks that do not tell theirs.
+    pub default_info_string: Option<String>,
+    pub relaxed_tasklist_matching: bool,
+    pub relaxed_autolinks: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Render {
+    /// Turn every line break into a `<br>`.
+    pub hardbreaks: bool,
+    pub github_pre_lang: bool,
+    pub full_info_string: bool,
+    /// Keep the raw html of the content and the dangerous links instead of
+    /// omitting them.
+    #[serde(rename = "unsafe")]
+    pub unsafe_html: bool,
+    /// Escape the raw html instead of omitting it.
+    pub escape: bool,
+    pub sourcepos: bool,
+    pub figure_with_caption: bool,
+    pub tasklist_classes: bool,
+}
+
+impl Markdown {
+    /// These options with the `overrides` of a page, if any.
+    pub fn with(&self, overrides: Option<&Value>) -> Result<Self, serde_json::Error> {
+        let Some(overrides) = overrides else {
+            return Ok(self.clone());
+        };
+        if overrides.get("highlight").is_some() {
+            return Err(serde::de::Error::custom("highlight can only be set for the whole site"));
+        }
+        let mut options = serde_json::to_value(self)?;
+        merge(&mut options, overrides.clone());
+        serde_json::from_value(options)
+    }
+
+    pub fn options(&self) -> Options<'static> {
+        let mut options = Options::default();
+        let extension = &self.extension;
+        options.extension.strikethrough = extension.strikethrough;
+        options.extension.tagfilter = extension.tagfilter;
+        options.extension.table = extension.table;
+        options.extension.autolink = extension.autolink;
+        options.extension.tasklist = extension.tasklist;
+        options.extension.superscript = extension.superscript;
+        options.extension.subscript = extension.subscript;
+        options.extension.footnotes = extension.footnotes;
+        options.extension.description_lists = extension.description_lists;
+        options.extension.multiline_block_quotes = extension.multiline_block_quotes;
+        options.extension.math_dollars = extension.math_dollars;
+        options.extension.math_code = extension.math_code;
+        options.extension.underline = extension.underline;
+        options.extension.spoiler = extension.spoiler;
+        let parse = &self.parse;
+        options.parse.smart = parse.smart;
+        options.parse.default_info_string = parse.default_info_string.clone();
+        options.parse.relaxed_tasklist_matching = parse.relaxed_tasklist_matching;
+        options.parse.relaxed_autolinks = parse.relaxed_autolinks;
+        let render = &self.render;
+        options.render.hardbreaks = render.hardbreaks;
+        options.render.github_pre_lang = render.github_pre_lang;
+        options.render.full_info_string = render.full_info_string;
+        options.render.unsafe_ = render.unsafe_html;
+        options.render.escape = render.escape;
+        options.render.sourcepos = render.sourcepos;
+        options.render.figure_with_caption = render.figure_with_caption;
+        options.render.tasklist_classes = render.tasklist_classes;
+        options
+    }
+
+    /// Render markdown content into html, giving every heading an id.
+    /// Every page and index is rendered here. The summary ends at a
+    /// `<!-- more -->` line, that is left out of the content.
+    pub fn render(&self, content: &str) -> Rendered {
+        let options = self.options();
+        let arena = Arena::new();
+        let root = parse_document(&arena, content, &options);
+        if self.highlight.enabled {
+            for node in root.descendants() {
+                let mut data = node.data.borrow_mut();
+                if let NodeValue::CodeBlock(block) = &data.value {
+                    if block.fenced && !(block.info == "math" && self.extension.math_code) {
+                        data.value = NodeValue::Raw(highlight::highlight(&block.literal, &block.info));
+                    }
+                }
+            }
+        }
+        let children: Vec<&AstNode> = root.children().collect();
+        let summary: Vec<&AstNode> = match children.iter().position(|node| is_more(node)) {
+            Some(position) => {
+                children[position].detach();
+                children[..position].to_vec()
+            },
+            None => children.iter()
+                .find(|node| matches!(node.data.borrow().value, NodeValue::Paragraph))
+                .into_iter()
+                .copied()
+                .collect(),
+        };
+        let text = if summary.is_empty() {
+            plain_text(&[root])
+        } else {
+            plain_text(&summary)
+        };
+        let collector = Collector::new(&self.headings);
+        let content = format(&[root], &options, Some(&collector));
+        Rendered {
+            // listings show it next to the whole page, so its headings go
+            // without ids, that would be repeated
+            summary: format(&summary, &options, None),
+            text,
+            content,
+            headings: collector.headings(),
+        }
+    }
+}
+
+/// Html of `nodes`, one after the other, with the ids and anchors of the
+/// headings from `collector`, or plain headings without it.
+fn format<'a>(nodes: &[&'a AstNode<'a>], options: &Options, collector: Option<&Collector>) -> String {
+    let mut plugins = Plugins::default();
+    plugins.render.heading_adapter = collector.map(|collector| collector as &dyn HeadingAdapter);
+    let mut html = Vec::new();
+    for node in nodes {
+        if let Err(e) = format_html_with_plugins(node, options, &mut html, &plugins) {
+            error!("Can not render markdown. {}", e);
+        }
+    }
+    String::from_utf8_lossy(&html).to_string()
+}
+
+/// Whether `node` is the `<!-- more -->` separator, in a line of its own.
+fn is_more(node: &AstNode) -> bool {
+    match &node.data.borrow().value {
+        NodeValue::HtmlBlock(block) => block.literal.split_whitespace()
+            .collect::<String>()
+            .eq_ignore_ascii_case("<!--more-->"),
+        _ => false,
+    }
+}
+
+/// Text of `nodes` without markup, in a single line.
+fn plain_text<'a>(nodes: &[&'a AstNode<'a>]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        for descendant in node.descendants() {
+            match &descendant.data.borrow().value {
+                NodeValue::Text(literal) => text.push_str(literal),
+                NodeValue::Code(code) => text.push_str(&code.literal),
+                NodeValue::SoftBreak | NodeValue::LineBreak => text.push(' '),
+                value if value.block() => text.push(' '),
+                _ => {},
+            }
+        }
+    }
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
@@ -4,6 +4,8 @@ use chrono::{DateTime, FixedOffset};
 use tracing::debug;
 use std::collections::HashMap;
 use slug::slugify;
+use serde_json::Value;
+use super::{Episode, Toc};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Metadata {
@@ -14,24 +16,106 @@ pub struct Metadata {
     pub excerpt: String,
     #[serde(default)]
     pub slug: String,
+    /// Custom values for the templates, of any type the front matter can
+    /// hold, like lists of links or nested maps.
     #[serde(default)]
-    pub vars: HashMap<String, String>,
+    pub vars: HashMap<String, Value>,
     #[serde(default)]
     pub tags: Vec<String>,
     pub publicated: bool,
     pub template: String,
+    /// Position of the page when its index is sorted by weight.
+    #[serde(default)]
+    pub weight: i64,
+    /// Pinned pages are listed before the rest in their index.
+    #[serde(default)]
+    pub pinned: bool,
+    /// How the pages of an index are sorted. Only used in `index.md`.
+    #[serde(default)]
+    pub sort_by: SortBy,
+    /// Defaults to descending for dates and ascending for the rest.
+    #[serde(default)]
+    pub order: Option<Order>,
+    /// Pages per page of an index, overriding the site default. Only used
+    /// in `index.md`.
+    #[serde(default)]
+    pub paginate: Option<usize>,
+    /// List the pages of every subfolder too. Only used in `index.md`.
+    #[serde(default)]
+    pub recursive: bool,
+    /// Permalink pattern of the page, or of the pages of the folder in
+    /// `index.md`, like `/:year/:month/:slug/`.
+    #[serde(default)]
+    pub permalink: Option<String>,
+    /// Literal path of the page in the site, overriding any pattern.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Previous paths of the page, that redirect to it.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Options of the markdown renderer for this page, overriding the ones
+    /// of the site.
+    #[serde(default)]
+    pub markdown: Option<Value>,
+    /// Table of contents of the page.
+    #[serde(default)]
+    pub toc: Toc,
+    /// Audio of the page when it is a podcast episode.
+    #[serde(default)]
+    pub episode: Option<Episode>,
+    /// Any other key of the front matter, like the terms of the
+    /// taxonomies declared in the configuration. They are kept as they are
+    /// and exposed to templates next to the known ones.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    #[default]
+    Date,
+    Title,
+    Weight,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    Asc,
+    Desc,
 }
 
 impl Metadata {
-    pub fn init(&mut self) {
+    /// Fill the slug from the title, and the excerpt from the
+    /// `description` of the content, or from the title when it has none.
+    pub fn init(&mut self, description: &str) {
         if self.slug.is_empty() {
             self.slug = slugify(&self.title);
         }
         if self.excerpt.is_empty() {
-            self.excerpt = self.title.clone();
-            self.excerpt.truncate(150);
+            self.excerpt = if description.is_empty() {
+                self.title.chars().take(150).collect()
+            } else {
+                description.to_string()
+            };
         }
     }
+    /// Names of the terms of the taxonomy `name`, that can be a single
+    /// string or a list of them in the front matter.
+    pub fn terms(&self, name: &str) -> Vec<String> {
+        if name == "tags" {
+            return self.tags.clone();
+        }
+        match self.extra.get(name) {
+            Some(Value::String(term)) => vec![term.clone()],
+            Some(Value::Array(terms)) => terms.iter()
+                .filter_map(|term| term.as_str().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
     pub fn validate(&self) -> Result<(), Box<dyn Error>>{
         debug!("Validating metadata: {:?}", self);
         if self.title.is_empty() {
@@ -46,6 +130,9 @@ impl Metadata {
         if self.template.is_empty() {
             return Err("Template is required".into());
         }
+        if !(1..=6).contains(&self.toc.depth) {
+            return Err(format!("Toc depth must be from 1 to 6, not {}", self.toc.depth).into());
+        }
         Ok(())
     }
 }
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use minijinja::{
     Environment,
@@ -12,6 +12,7 @@ use minijinja::{
     },
 };
 use once_cell::sync::Lazy;
+use tracing::debug;
 use chrono::{DateTime, FixedOffset};
 use chrono_tz::Tz;
 
@@ -21,12 +22,33 @@ mod config;
 mod index;
 mod site;
 mod pageerror;
+mod feed;
+mod defaults;
+mod frontmatter;
+mod builderror;
+mod markdown;
+mod toc;
+pub mod highlight;
+mod podcast;
+mod taxonomy;
+pub mod archive;
+pub mod redirect;
+mod paginator;
 mod publishers;
 
-pub use metadata::Metadata;
+pub use defaults::{Defaults, DEFAULTS_FILE};
+pub use frontmatter::{Format, FrontMatter, FrontMatterError};
+pub use builderror::BuildError;
+pub use markdown::Markdown;
+pub use metadata::{Metadata, Order, SortBy};
 pub use page::Page;
 pub use pageerror::create_page_error;
-pub use index::Index;
+pub use index::{Index, Section};
+pub use paginator::Paginator;
+pub use feed::{Feed, FeedLinks, Feeds};
+pub use podcast::{Episode, Podcast};
+pub use taxonomy::{Taxonomy, Term};
+pub use toc::{Heading, Toc};
 pub use config::Config;
 pub use site::Site;
 pub use publishers::Mastodon;
@@ -44,6 +66,31 @@ pub static ENV: Lazy<Environment<'static>> = Lazy::new(|| {
     env
 });
 
+/// Render `template` into `destination_file`, creating its folder, and
+/// return the generated file.
+pub async fn render(template: &str, ctx: Value, destination_file: &Path) -> Result<PathBuf, BuildError> {
+    if let Some(folder) = destination_file.parent() {
+        tokio::fs::create_dir_all(folder).await
+            .map_err(|cause| BuildError::Write { path: folder.to_path_buf(), cause })?;
+    }
+    let rendered = ENV.get_template(template)
+        .map_err(|cause| BuildError::Template {
+            path: destination_file.to_path_buf(),
+            template: template.to_string(),
+            cause,
+        })?
+        .render(&ctx)
+        .map_err(|cause| BuildError::Render {
+            path: destination_file.to_path_buf(),
+            template: template.to_string(),
+            cause,
+        })?;
+    tokio::fs::write(destination_file, rendered).await
+        .map_err(|cause| BuildError::Write { path: destination_file.to_path_buf(), cause })?;
+    debug!("Generated {:?}", destination_file);
+    Ok(destination_file.to_path_buf())
+}
+
 fn striptags(value: String) -> String {
     let mut data = String::new();
     let mut inside = false;
@@ -107,25 +154,27 @@ fn value_to_chrono_datetime(
 }
 
 pub fn date(_state: &State, value: Value, kwargs: Kwargs) -> Result<String, Error> {
-    let format = kwargs.get::<Option<&str>>("format")?;
+    let format = kwargs.get::<Option<&str>>("format")?.unwrap_or("%Y-%m-%d");
+    let datetime = value_to_chrono_datetime(value)?;
     match kwargs.get::<Option<&str>>("timezone")?{
         Some(timezone) => {
-            let tz: Tz = timezone.parse().unwrap();
-            let datetime = value_to_chrono_datetime(value).unwrap().with_timezone(&tz);
-            Ok(format!("{}", datetime.format(format.unwrap())))
+            let tz: Tz = timezone.parse().map_err(|e| Error::new(
+                ErrorKind::InvalidOperation,
+                format!("Invalid timezone {}. {}", timezone, e),
+            ))?;
+            Ok(format!("{}", datetime.with_timezone(&tz).format(format)))
         },
         None => {
-            let datetime = value_to_chrono_datetime(value).unwrap();
-            Ok(format!("{}", datetime.format(format.unwrap())))
+            Ok(format!("{}", datetime.format(format)))
 
         },
     }
 }
 
 pub fn truncate(_state: &State, value: Value, kwargs: Kwargs) -> Result<String, Error> {
-    let length = kwargs.get::<Option<usize>>("length")?.unwrap();
+    let length = kwargs.get::<usize>("length")?;
     match value.as_str() {
-        Some(s) => match value.as_str().unwrap().char_indices().nth(length) {
+        Some(s) => match s.char_indices().nth(length) {
             None => Ok(s.to_string()),
             Some((idx, _)) => Ok(s[..idx].to_string()),
         },
@@ -1,92 +1,234 @@
-use super::{Metadata, Site, ENV};
-use gray_matter::{engine::YAML, Matter};
+use super::{BuildError, Defaults, FrontMatter, Heading, Metadata, Site, Term, ENV};
+use std::collections::HashMap;
 use minijinja::context;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use tracing::{debug, error};
-use comrak::{markdown_to_html, Options};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Page {
     pub route: PathBuf,
+    /// Markdown file the page is read from.
+    #[serde(skip)]
+    pub source: PathBuf,
     pub metadata: Metadata,
     pub content: String,
+    /// Headings of the content, nested and down to the depth of the `toc`
+    /// of the front matter, each one with the id of its element.
+    #[serde(default)]
+    pub toc: Vec<Heading>,
+    /// Html of the content before `<!-- more -->`, or of its first
+    /// paragraph.
+    #[serde(default)]
+    pub summary: String,
+    /// Plain text for listings and meta tags, being the excerpt of the
+    /// front matter or else the first words of the summary.
+    #[serde(default)]
+    pub description: String,
+    /// The page is not publicated and only generated for preview.
+    #[serde(default)]
+    pub draft: bool,
+    /// Tags of the page, with the url of their listings.
+    #[serde(default)]
+    pub tags: Vec<Term>,
+    /// Terms of the page for every taxonomy, by taxonomy name.
+    #[serde(default)]
+    pub taxonomies: HashMap<String, Vec<Term>>,
+    /// Path of the page in the site, like `/blog/hello-world/`.
+    #[serde(default)]
+    pub permalink: String,
+    /// Permalink joined to the url of the site.
+    #[serde(default)]
+    pub url: String,
 }
 
 impl Page {
-    pub async fn read(route: &Path, source: &PathBuf) -> Option<Self> {
-        if let Ok(data) = tokio::fs::read_to_string(&source).await {
-            let matter = Matter::<YAML>::new();
-            let result = matter.parse(&data);
-            if let Ok(serialized_data) = result.data.ok_or("Can not read metadata") {
-                if let Ok(mut metadata) = serialized_data.deserialize::<Metadata>() {
-                    metadata.init();
-                    if let Ok(()) = metadata.validate() {
-                        return Some(Self {
-                            route: route.to_path_buf(),
-                            metadata,
-                            content: markdown_to_html(&result.content, &Options::default()),
-                        });
-                    } else {
-                        error!("Can not validate metadata for {:?}", source);
-                    }
-                } else {
-                    error!("Can not deserialize metadata for {:?}", source);
-                }
-            } else {
-                error!("Can not read metadata for {:?}", source);
-            }
-        } else {
-            error!("Can not read {:?}", source);
-        }
-        None
+    /// Read a markdown page, completing its front matter with the `defaults`
+    /// of its folder. Its permalink comes from the `url` or the `permalink`
+    /// of its front matter, or else from the pattern of the folder, or the
+    /// one of the site.
+    pub async fn read(site: &Site, route: &Path, source: &PathBuf, defaults: &Defaults) -> Result<Self, BuildError> {
+        let data = tokio::fs::read_to_string(&source).await
+            .map_err(|cause| BuildError::Read { path: source.clone(), cause })?;
+        let result = FrontMatter::parse(&data)
+            .map_err(|cause| BuildError::FrontMatter { path: source.clone(), cause })?
+            .ok_or_else(|| BuildError::MissingFrontMatter { path: source.clone() })?;
+        let mut metadata: Metadata = serde_path_to_error::deserialize(defaults.apply(result.data.clone()))
+            .map_err(|cause| BuildError::Metadata { path: source.clone(), format: result.format, location: result.locate(cause.path()), cause })?;
+        let markdown = site.markdown.with(metadata.markdown.as_ref())
+            .map_err(|e| BuildError::Invalid { path: source.clone(), message: format!("Invalid markdown options. {}", e) })?;
+        let rendered = markdown.render(&result.content);
+        metadata.init(&rendered.description(site.description_words));
+        metadata.validate()
+            .map_err(|e| BuildError::Invalid { path: source.clone(), message: e.to_string() })?;
+        let taxonomies: HashMap<String, Vec<Term>> = site.taxonomies.iter()
+            .map(|taxonomy| (taxonomy.name.clone(), taxonomy.terms(site, &metadata.terms(&taxonomy.name))))
+            .collect();
+        let permalink = match &metadata.url {
+            Some(url) => normalize_permalink(url),
+            None => {
+                let pattern = metadata.permalink.as_deref()
+                    .or(defaults.permalink.as_deref())
+                    .unwrap_or(&site.permalink);
+                expand_permalink(pattern, route, &metadata)
+            },
+        }.map_err(|message| BuildError::Invalid { path: source.clone(), message })?;
+        Ok(Self {
+            route: route.to_path_buf(),
+            source: source.clone(),
+            draft: !metadata.publicated,
+            tags: taxonomies.get("tags").cloned().unwrap_or_default(),
+            taxonomies,
+            url: site_url(site, &permalink),
+            permalink,
+            toc: Heading::tree(&rendered.headings, metadata.toc.depth),
+            summary: rendered.summary,
+            description: metadata.excerpt.clone(),
+            metadata,
+            content: rendered.content,
+        })
     }
 
-    pub async fn generate(&self, site: &Site, parent: &PathBuf) {
+    /// Render the page into `destination/<permalink>/index.html`, or into
+    /// `destination/<permalink>` when the permalink is a file, returning the
+    /// generated file.
+    pub async fn generate(&self, site: &Site, destination: &Path) -> Result<PathBuf, BuildError> {
         debug!(
             "--- Start generation {:?} - {}",
-            &parent, &self.metadata.slug
+            &destination, &self.permalink
         );
-        debug!("Parent: {:?}", parent);
-        debug!("Slug: {:?}", self.metadata.slug);
-        let destination_folder = PathBuf::new().join(parent).join(&self.metadata.slug);
+        debug!("Permalink: {:?}", self.permalink);
+        let relative = self.permalink.trim_start_matches('/');
+        let (destination_folder, destination_file) = if Path::new(relative).extension().is_some() {
+            let destination_file = destination.join(relative);
+            (destination_file.parent().unwrap_or(destination).to_path_buf(), destination_file)
+        } else {
+            let destination_folder = destination.join(relative);
+            let destination_file = destination_folder.join("index.html");
+            (destination_folder, destination_file)
+        };
         debug!("Destination folder: {:?}", &destination_folder);
-        let destination_file = PathBuf::new().join(&destination_folder).join("index.html");
         debug!("Destination file: {:?}", &destination_file);
-        match tokio::fs::create_dir_all(&destination_folder).await {
-            Ok(_) => {
-                debug!("Created folder: {:?}", &destination_folder);
-                if let Ok(true) = tokio::fs::try_exists(&destination_file).await {
-                    debug!("File exists. Overwriting {:?}", &destination_file);
-                    if let Ok(()) = tokio::fs::remove_file(&destination_file).await {
-                        debug!("Removed file: {:?}", &destination_file);
-                    } else {
-                        error!("Can not remove file: {:?}", &destination_file);
-                    }
-                }
-                let ctx = context!(
-                    site => site,
-                    page => self,
-                );
-                match ENV.get_template(&self.metadata.template) {
-                    Ok(template) => {
-                        match template.render(&ctx) {
-                            Ok(rendered) => {
-                                match tokio::fs::write(&destination_file, rendered).await {
-                                    Ok(()) => debug!("Save {:?}", &destination_file),
-                                    Err(e) => error!("Can not save {:?}. {}", &destination_file, e),
-                                }
-                            },
-                            Err(e) => error!("Can not render {:?}. {}", &destination_file, e),
-                        }
-                    }
-                    Err(e) => error!("Can not get template {:?}. {}", &self.metadata.template, e),
-                };
-            }
-            Err(err) => {
-                error!("Can not create folder: {:?}. {}", &destination_folder, err);
+        let ctx = context!(
+            site => site,
+            page => self,
+        );
+        let template = &self.metadata.template;
+        let rendered = ENV.get_template(template)
+            .map_err(|cause| BuildError::Template { path: self.source.clone(), template: template.clone(), cause })?
+            .render(&ctx)
+            .map_err(|cause| BuildError::Render { path: self.source.clone(), template: template.clone(), cause })?;
+        tokio::fs::create_dir_all(&destination_folder).await
+            .map_err(|cause| BuildError::Write { path: destination_folder.clone(), cause })?;
+        debug!("Created folder: {:?}", &destination_folder);
+        if let Ok(true) = tokio::fs::try_exists(&destination_file).await {
+            debug!("File exists. Overwriting {:?}", &destination_file);
+            if let Ok(()) = tokio::fs::remove_file(&destination_file).await {
+                debug!("Removed file: {:?}", &destination_file);
+            } else {
+                error!("Can not remove file: {:?}", &destination_file);
             }
         }
-        debug!("--- End generation {:?} - {}", &parent, &self.metadata.slug);
+        tokio::fs::write(&destination_file, rendered).await
+            .map_err(|cause| BuildError::Write { path: destination_file.clone(), cause })?;
+        debug!("Save {:?}", &destination_file);
+        debug!("--- End generation {:?} - {}", &destination, &self.permalink);
+        Ok(destination_file)
+    }
+}
+
+/// Replace the placeholders of a permalink pattern: `:year`, `:month`,
+/// `:day`, `:slug` and `:section`, the folder of the page.
+fn expand_permalink(pattern: &str, route: &Path, metadata: &Metadata) -> Result<String, String> {
+    let section = route.to_string_lossy();
+    let permalink = pattern
+        .replace(":year", &metadata.date.format("%Y").to_string())
+        .replace(":month", &metadata.date.format("%m").to_string())
+        .replace(":day", &metadata.date.format("%d").to_string())
+        .replace(":slug", &metadata.slug)
+        .replace(":section", &section);
+    normalize_permalink(&permalink)
+}
+
+/// Permalinks start with a slash, and end with one unless they are a file,
+/// without empty, `.` or `..` segments. A `..` removes the segment before
+/// it, and it is an error when there is none, as the permalink would be
+/// outside the site.
+pub fn normalize_permalink(permalink: &str) -> Result<String, String> {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in permalink.split('/') {
+        match segment {
+            "" | "." => {},
+            ".." => {
+                if segments.pop().is_none() {
+                    return Err(format!("Permalink {} is outside the site", permalink));
+                }
+            },
+            segment => segments.push(segment),
+        }
+    }
+    let path = segments.join("/");
+    Ok(if path.is_empty() {
+        "/".to_string()
+    } else if Path::new(&path).extension().is_some() {
+        format!("/{}", path)
+    } else {
+        format!("/{}/", path)
+    })
+}
+
+/// Join a permalink to the url of the site.
+pub fn site_url(site: &Site, permalink: &str) -> String {
+    format!("{}{}", site.url.trim_end_matches('/'), permalink)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(slug: &str) -> Metadata {
+        serde_json::from_value(serde_json::json!({
+            "title": "Hello",
+            "date": "2024-03-05T10:00:00+01:00",
+            "slug": slug,
+            "publicated": true,
+            "template": "page.html",
+        })).unwrap()
+    }
+
+    #[test]
+    fn normalize_adds_slashes_to_folders() {
+        assert_eq!(normalize_permalink("blog/hello").unwrap(), "/blog/hello/");
+        assert_eq!(normalize_permalink("//blog//hello//").unwrap(), "/blog/hello/");
+        assert_eq!(normalize_permalink("").unwrap(), "/");
+    }
+
+    #[test]
+    fn normalize_keeps_files_without_slash() {
+        assert_eq!(normalize_permalink("/feeds/podcast.xml").unwrap(), "/feeds/podcast.xml");
+    }
+
+    #[test]
+    fn normalize_resolves_dot_segments() {
+        assert_eq!(normalize_permalink("/blog/./hello/").unwrap(), "/blog/hello/");
+        assert_eq!(normalize_permalink("/blog/drafts/../hello/").unwrap(), "/blog/hello/");
+        assert_eq!(normalize_permalink("/blog/..").unwrap(), "/");
+    }
+
+    #[test]
+    fn normalize_rejects_paths_outside_the_site() {
+        assert!(normalize_permalink("/../hello/").is_err());
+        assert!(normalize_permalink("/blog/../../etc/passwd").is_err());
+    }
+
+    #[test]
+    fn expand_replaces_placeholders() {
+        let permalink = expand_permalink("/:section/:year/:month/:day/:slug/", Path::new("blog"), &metadata("hello"));
+        assert_eq!(permalink.unwrap(), "/blog/2024/03/05/hello/");
+    }
+
+    #[test]
+    fn expand_rejects_slugs_outside_the_site() {
+        assert!(expand_permalink("/:slug/", Path::new(""), &metadata("../../etc")).is_err());
     }
 }
@@ -0,0 +1,71 @@
+use serde::{Serialize, Deserialize};
+use std::path::{Path, PathBuf};
+use super::Page;
+
+/// One page of a paginated listing, as exposed to templates.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Paginator {
+    /// Number of this page, starting at 1.
+    pub current: usize,
+    /// Number of pages of the listing.
+    pub total: usize,
+    pub per_page: usize,
+    /// Number of items in the whole listing.
+    pub items: usize,
+    pub url: String,
+    pub first: String,
+    pub last: String,
+    pub previous: Option<String>,
+    pub next: Option<String>,
+    /// Items in this page.
+    pub pages: Vec<Page>,
+}
+
+impl Paginator {
+    /// Split `pages` in chunks of `per_page` items. The first one is served
+    /// from `base_url` and the rest from `base_url/page/<number>`. A
+    /// `per_page` of 0 keeps all of them in a single page.
+    pub fn paginate(pages: &[Page], per_page: usize, base_url: &str) -> Vec<Self> {
+        let per_page = if per_page == 0 { pages.len().max(1) } else { per_page };
+        let total = pages.len().div_ceil(per_page).max(1);
+        (1..=total)
+            .map(|current| {
+                let start = (current - 1) * per_page;
+                let end = (start + per_page).min(pages.len());
+                Self {
+                    current,
+                    total,
+                    per_page,
+                    items: pages.len(),
+                    url: Self::url(base_url, current),
+                    first: Self::url(base_url, 1),
+                    last: Self::url(base_url, total),
+                    previous: (current > 1).then(|| Self::url(base_url, current - 1)),
+                    next: (current < total).then(|| Self::url(base_url, current + 1)),
+                    pages: pages[start..end].to_vec(),
+                }
+            })
+            .collect()
+    }
+
+    /// Folder, relative to the listing, where this page is generated.
+    pub fn folder(&self) -> PathBuf {
+        if self.current == 1 {
+            PathBuf::new()
+        } else {
+            Path::new("page").join(self.current.to_string())
+        }
+    }
+
+    fn url(base_url: &str, number: usize) -> String {
+        if number == 1 {
+            base_url.to_string()
+        } else {
+            Path::new(base_url)
+                .join("page")
+                .join(number.to_string())
+                .to_string_lossy()
+                .to_string()
+        }
+    }
+}
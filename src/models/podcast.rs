@@ -0,0 +1,116 @@
+use serde::{Serialize, Deserialize};
+use std::path::Path;
+use super::feed::absolute_url;
+
+/// Podcast settings of the site. The episodes are the pages of `section`
+/// with an `episode` in their front matter.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Podcast {
+    /// Folder, relative to the source, that holds the episodes.
+    pub section: String,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub owner: String,
+    pub email: String,
+    /// Cover art, at least 1400x1400 pixels.
+    pub image: String,
+    pub category: String,
+    #[serde(default)]
+    pub subcategory: Option<String>,
+    #[serde(default)]
+    pub explicit: bool,
+    /// `episodic` or `serial`.
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    /// Podcasting 2.0 `podcast:guid`.
+    #[serde(default)]
+    pub guid: Option<String>,
+    #[serde(default)]
+    pub locked: bool,
+}
+
+fn default_kind() -> String {
+    "episodic".to_string()
+}
+
+impl Podcast {
+    /// Same podcast with its image turned into an absolute url.
+    pub fn absolute(&self, base: &str) -> Self {
+        Self {
+            image: absolute(base, &self.image),
+            ..self.clone()
+        }
+    }
+}
+
+/// Audio of a page that is a podcast episode.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Episode {
+    /// Path in the site or url of the audio file.
+    pub audio: String,
+    /// Size of the audio file in bytes.
+    pub length: u64,
+    #[serde(default = "default_mime")]
+    pub mime: String,
+    /// Duration in seconds or as `HH:MM:SS`.
+    #[serde(default)]
+    pub duration: Option<String>,
+    #[serde(default)]
+    pub season: Option<u32>,
+    #[serde(default)]
+    pub number: Option<u32>,
+    /// `full`, `trailer` or `bonus`.
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub explicit: bool,
+    #[serde(default)]
+    pub image: Option<String>,
+    /// Path in the site or url of the transcript (`.srt`, `.vtt`, `.json`
+    /// or `.html`).
+    #[serde(default)]
+    pub transcript: Option<String>,
+    /// Path in the site or url of the JSON chapters file.
+    #[serde(default)]
+    pub chapters: Option<String>,
+}
+
+fn default_mime() -> String {
+    "audio/mpeg".to_string()
+}
+
+impl Episode {
+    /// Same episode with its paths turned into absolute urls.
+    pub fn absolute(&self, base: &str) -> Self {
+        let url = |value: &String| absolute(base, value);
+        Self {
+            audio: url(&self.audio),
+            image: self.image.as_ref().map(url),
+            transcript: self.transcript.as_ref().map(url),
+            chapters: self.chapters.as_ref().map(url),
+            ..self.clone()
+        }
+    }
+
+    /// Mime type of the transcript, from its extension.
+    pub fn transcript_type(&self) -> Option<&'static str> {
+        let transcript = self.transcript.as_ref()?;
+        match Path::new(transcript).extension()?.to_str()? {
+            "srt" => Some("application/srt"),
+            "vtt" => Some("text/vtt"),
+            "json" => Some("application/json"),
+            "html" | "htm" => Some("text/html"),
+            _ => Some("text/plain"),
+        }
+    }
+}
+
+/// `value` as it is when it is an url, or else joined to the site url.
+fn absolute(base: &str, value: &str) -> String {
+    if value.starts_with("http://") || value.starts_with("https://") {
+        value.to_string()
+    } else {
+        absolute_url(base, Path::new(value))
+    }
+}
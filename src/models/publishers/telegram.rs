@@ -12,6 +12,7 @@ pub struct Telegram{
 }
 
 impl Telegram {
+    #[allow(dead_code)]
     fn prepare(text: &str) -> String{
         text.chars()
             .map(|c| match c {
@@ -0,0 +1,276 @@
+use serde::{Serialize, Deserialize};
+use minijinja::context;
+use tracing::{debug, error};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use super::page::normalize_permalink;
+use super::{render, BuildError, Page, Site};
+
+/// File, in the destination folder, with the redirects the server answers.
+pub const REDIRECTS_FILE: &str = "redirects.json";
+const TEMPLATE: &str = "redirect.html";
+
+/// An old address of the site that now lives at `to`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Redirect {
+    pub from: String,
+    /// Path in the site or absolute url.
+    pub to: String,
+    /// 301 (permanent) or 308 (permanent, keeping the method).
+    #[serde(default = "default_status")]
+    pub status: u16,
+}
+
+fn default_status() -> u16 {
+    301
+}
+
+/// Redirects of a generated site, by the path they redirect from.
+#[derive(Debug, Default, Clone)]
+pub struct Redirects {
+    table: HashMap<String, Redirect>,
+}
+
+impl Redirects {
+    /// Redirect for the request `path`, if any.
+    pub fn find(&self, path: &str) -> Option<&Redirect> {
+        self.table.get(&normalize(path))
+    }
+}
+
+/// Key used to look up a path, without leading or trailing slashes.
+pub fn normalize(path: &str) -> String {
+    path.trim_matches('/').to_string()
+}
+
+/// File, relative to the destination folder, with the page redirecting
+/// from the normalized path `from`.
+pub fn output(from: &str) -> PathBuf {
+    if Path::new(from).extension().is_some() {
+        PathBuf::from(from)
+    } else {
+        Path::new(from).join("index.html")
+    }
+}
+
+/// Read the site-wide redirects file, a YAML list of redirects.
+pub async fn read(path: &Path) -> Result<Vec<Redirect>, BuildError> {
+    let content = tokio::fs::read_to_string(path).await
+        .map_err(|cause| BuildError::Read { path: path.to_path_buf(), cause })?;
+    serde_yaml::from_str(&content)
+        .map_err(|e| BuildError::Redirect { path: path.to_path_buf(), message: format!("Can not parse. {}", e) })
+}
+
+/// Redirects from the `aliases` of the pages plus the ones in the site-wide
+/// `file`, read from `file_path`. Redirects from outside the site, that
+/// collide with any of the `outputs` of the rest of the site or with another
+/// redirect, or that end in a loop, are discarded and reported as errors of
+/// the file they come from.
+pub fn collect(site: &Site, pages: &[Page], outputs: &HashSet<PathBuf>, file: Vec<Redirect>, file_path: &Path) -> (Vec<Redirect>, Vec<BuildError>) {
+    let mut errors = Vec::new();
+    let aliases = pages.iter().flat_map(|page| {
+        page.metadata.aliases.iter().map(|alias| (page.source.clone(), Redirect {
+            from: alias.clone(),
+            to: page.permalink.clone(),
+            status: default_status(),
+        }))
+    });
+    let file = file.into_iter().map(|redirect| (file_path.to_path_buf(), redirect));
+    let mut targets: HashMap<String, Redirect> = HashMap::new();
+    let mut redirects = Vec::new();
+    for (path, mut redirect) in aliases.chain(file) {
+        let error = |message: String| BuildError::Redirect { path: path.clone(), message };
+        let from = match normalize_permalink(&redirect.from) {
+            Ok(from) => normalize(&from),
+            Err(_) => {
+                errors.push(error(format!("Redirect from {} is outside the site", redirect.from)));
+                continue;
+            },
+        };
+        if !matches!(redirect.status, 301 | 302 | 307 | 308) {
+            errors.push(error(format!("Invalid status {} for redirect from {}", redirect.status, redirect.from)));
+        } else if outputs.contains(&output(&from)) {
+            errors.push(error(format!("Redirect from {} collides with {}", redirect.from, output(&from).display())));
+        } else if let Some(other) = targets.get(&from) {
+            if other.to != redirect.to {
+                errors.push(error(format!("Redirect from {} goes to {} and to {}", redirect.from, other.to, redirect.to)));
+            }
+        } else {
+            redirect.from = from.clone();
+            targets.insert(from, redirect.clone());
+            redirects.push((path, redirect));
+        }
+    }
+    redirects.retain(|(path, redirect)| {
+        let mut visited = HashSet::new();
+        let mut current = normalize(&redirect.from);
+        while let Some(next) = targets.get(&current) {
+            if !visited.insert(current.clone()) {
+                errors.push(BuildError::Redirect {
+                    path: path.clone(),
+                    message: format!("Redirect from {} ends in a loop", redirect.from),
+                });
+                return false;
+            }
+            current = normalize(next.to.strip_prefix(site.url.trim_end_matches('/')).unwrap_or(&next.to));
+        }
+        true
+    });
+    (redirects.into_iter().map(|(_, redirect)| redirect).collect(), errors)
+}
+
+/// Write the redirects for the server into `destination/redirects.json`,
+/// and a page redirecting with a meta refresh for every one of them, for
+/// static hosting. Returns the generated files.
+pub async fn generate(site: &Site, destination: &Path, redirects: &[Redirect]) -> Vec<Result<PathBuf, BuildError>> {
+    let mut generated = Vec::new();
+    let table: HashMap<String, &Redirect> = redirects.iter()
+        .map(|redirect| (normalize(&redirect.from), redirect))
+        .collect();
+    let destination_file = destination.join(REDIRECTS_FILE);
+    match serde_json::to_string_pretty(&table) {
+        Ok(content) => match tokio::fs::write(&destination_file, content).await {
+            Ok(()) => {
+                debug!("Generated {:?}", &destination_file);
+                generated.push(Ok(destination_file));
+            },
+            Err(cause) => generated.push(Err(BuildError::Write { path: destination_file, cause })),
+        },
+        Err(e) => error!("Can not serialize redirects. {}", e),
+    }
+    for (from, redirect) in table {
+        let to = if redirect.to.contains("://") {
+            redirect.to.clone()
+        } else {
+            format!("{}/{}", site.url.trim_end_matches('/'), redirect.to.trim_start_matches('/'))
+        };
+        let ctx = context!(
+            site => site,
+            redirect => redirect,
+            to => to,
+        );
+        let destination_file = destination.join(output(&from));
+        generated.push(render(TEMPLATE, ctx, &destination_file).await);
+    }
+    generated
+}
+
+/// Read the redirects of a generated site, that has none when it has no
+/// redirects file.
+pub async fn load(destination: &Path) -> Redirects {
+    let Ok(content) = tokio::fs::read_to_string(destination.join(REDIRECTS_FILE)).await else {
+        return Redirects::default();
+    };
+    match serde_json::from_str(&content) {
+        Ok(table) => Redirects { table },
+        Err(e) => {
+            error!("Can not parse {:?}. {}", destination.join(REDIRECTS_FILE), e);
+            Redirects::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn site() -> Site {
+        serde_json::from_value(json!({
+            "url": "https://example.com/",
+            "language": "en",
+            "language_direction": "ltr",
+            "theme": "light",
+            "title": "Site",
+            "description": "",
+            "author": "",
+            "avatar": "",
+            "email": "",
+        })).unwrap()
+    }
+
+    fn redirect(from: &str, to: &str) -> Redirect {
+        Redirect { from: from.to_string(), to: to.to_string(), status: 301 }
+    }
+
+    fn collect_file(file: Vec<Redirect>, outputs: &[&str]) -> (Vec<Redirect>, Vec<BuildError>) {
+        let outputs = outputs.iter().map(PathBuf::from).collect();
+        collect(&site(), &[], &outputs, file, Path::new("redirects.yml"))
+    }
+
+    #[test]
+    fn aliases_redirect_to_their_page() {
+        let page: Page = serde_json::from_value(json!({
+            "route": "blog",
+            "metadata": {
+                "title": "Hello",
+                "publicated": true,
+                "template": "page.html",
+                "aliases": ["/old/hello/"],
+            },
+            "content": "",
+            "permalink": "/blog/hello/",
+        })).unwrap();
+        let (redirects, errors) = collect(&site(), &[page], &HashSet::new(), Vec::new(), Path::new("redirects.yml"));
+        assert!(errors.is_empty());
+        assert_eq!(redirects, vec![redirect("old/hello", "/blog/hello/")]);
+    }
+
+    #[test]
+    fn from_is_normalized() {
+        let (redirects, errors) = collect_file(vec![redirect("/old/./drafts/../hello/", "/blog/hello/")], &[]);
+        assert!(errors.is_empty());
+        assert_eq!(redirects[0].from, "old/hello");
+    }
+
+    #[test]
+    fn from_outside_the_site_is_rejected() {
+        let (redirects, errors) = collect_file(vec![redirect("/../../etc/passwd", "/")], &[]);
+        assert!(redirects.is_empty());
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn collisions_with_generated_files_are_rejected() {
+        let file = vec![
+            redirect("/blog/hello/", "/new/"),
+            redirect("/rss.xml", "/feed.xml"),
+        ];
+        let (redirects, errors) = collect_file(file, &["blog/hello/index.html", "rss.xml"]);
+        assert!(redirects.is_empty());
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn conflicting_redirects_keep_the_first() {
+        let file = vec![
+            redirect("/old/", "/a/"),
+            redirect("/old/", "/a/"),
+            redirect("/old/", "/b/"),
+        ];
+        let (redirects, errors) = collect_file(file, &[]);
+        assert_eq!(redirects, vec![redirect("old", "/a/")]);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn loops_are_rejected() {
+        let file = vec![
+            redirect("/a/", "/b/"),
+            redirect("/b/", "https://example.com/a/"),
+            redirect("/c/", "/a/"),
+            redirect("/d/", "/e/"),
+        ];
+        let (redirects, errors) = collect_file(file, &[]);
+        assert_eq!(redirects, vec![redirect("d", "/e/")]);
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn invalid_status_is_rejected() {
+        let file = vec![Redirect { status: 200, ..redirect("/old/", "/new/") }];
+        let (redirects, errors) = collect_file(file, &[]);
+        assert!(redirects.is_empty());
+        assert_eq!(errors.len(), 1);
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Serialize, Deserialize};
-use super::{Telegram, Mastodon};
+use super::{Feeds, Markdown, Podcast, Taxonomy, Telegram, Mastodon};
+use super::taxonomy::default_taxonomies;
 use std::fmt::{self, Display};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -16,6 +17,42 @@ pub struct Site{
     pub telegram: Option<Telegram>,
     pub mastodon: Option<Mastodon>,
     pub socials: Option<Vec<Social>>,
+    #[serde(default)]
+    pub feeds: Feeds,
+    pub podcast: Option<Podcast>,
+    #[serde(default = "default_taxonomies")]
+    pub taxonomies: Vec<Taxonomy>,
+    /// Timezone used to group the pages in the archive.
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// Generate the archive of pages by year and month.
+    #[serde(default = "default_archive")]
+    pub archive: bool,
+    /// Permalink pattern of the pages. See `Page::read`.
+    #[serde(default = "default_permalink")]
+    pub permalink: String,
+    /// Options of the markdown renderer.
+    #[serde(default)]
+    pub markdown: Markdown,
+    /// Words of the description of the pages, taken from their summary.
+    #[serde(default = "default_description_words")]
+    pub description_words: usize,
+}
+
+fn default_permalink() -> String {
+    "/:section/:slug/".to_string()
+}
+
+fn default_description_words() -> usize {
+    50
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_archive() -> bool {
+    true
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -0,0 +1,150 @@
+use serde::{Serialize, Deserialize};
+use minijinja::context;
+use slug::slugify;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use super::{render, BuildError, Feed, FeedLinks, Page, Paginator, Site};
+
+/// A way of classifying pages, like tags, categories or series. The terms
+/// of a page are read from the front matter key with the same name.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Taxonomy {
+    pub name: String,
+    /// Folder, relative to the site, where the taxonomy is generated.
+    /// Defaults to the name.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Template listing every term.
+    #[serde(default = "default_list_template")]
+    pub list_template: String,
+    /// Template listing the pages of a term.
+    #[serde(default = "default_term_template")]
+    pub term_template: String,
+    /// Paginate the listing of every term like the indexes.
+    #[serde(default = "default_true")]
+    pub paginate: bool,
+    /// Generate feeds for every term.
+    #[serde(default = "default_true")]
+    pub feed: bool,
+}
+
+fn default_list_template() -> String {
+    "taxonomy.html".to_string()
+}
+
+fn default_term_template() -> String {
+    "term.html".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Taxonomies when none are configured.
+pub fn default_taxonomies() -> Vec<Taxonomy> {
+    vec![Taxonomy::tags()]
+}
+
+impl Taxonomy {
+    pub fn tags() -> Self {
+        Self {
+            name: "tags".to_string(),
+            prefix: None,
+            list_template: default_list_template(),
+            term_template: default_term_template(),
+            paginate: true,
+            feed: true,
+        }
+    }
+
+    pub fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Terms of this taxonomy for the given names.
+    pub fn terms(&self, site: &Site, names: &[String]) -> Vec<Term> {
+        names.iter()
+            .map(|name| Term::new(site, self, name, 0))
+            .collect()
+    }
+
+    /// Render the list of terms into `destination/<prefix>/` and a listing
+    /// for every term into `destination/<prefix>/<term>/`, returning the
+    /// generated files.
+    pub async fn generate(&self, site: &Site, destination: &Path, pages: &[Page], paginate: usize) -> Vec<Result<PathBuf, BuildError>> {
+        let mut generated = Vec::new();
+        let mut groups: BTreeMap<String, (String, Vec<Page>)> = BTreeMap::new();
+        for page in pages {
+            for name in page.metadata.terms(&self.name) {
+                groups.entry(slugify(&name))
+                    .or_insert_with(|| (name.clone(), Vec::new()))
+                    .1
+                    .push(page.clone());
+            }
+        }
+        let folder = destination.join(self.prefix());
+        let paginate = if self.paginate { paginate } else { 0 };
+        let terms: Vec<Term> = groups.values()
+            .map(|(name, pages)| Term::new(site, self, name, pages.len()))
+            .collect();
+        let ctx = context!(
+            site => site,
+            taxonomy => self,
+            terms => terms,
+        );
+        generated.push(render(&self.list_template, ctx, &folder.join("index.html")).await);
+
+        for (term, (_, mut pages)) in terms.into_iter().zip(groups.into_values()) {
+            pages.sort_by_key(|page| Reverse(page.metadata.date));
+            let route = Path::new(self.prefix()).join(&term.slug);
+            let term_folder = destination.join(&route);
+            let feed = (site.feeds.enabled && self.feed)
+                .then(|| FeedLinks::new(site, &term.name, &route));
+            for paginator in Paginator::paginate(&pages, paginate, &term.url) {
+                let ctx = context!(
+                    site => site,
+                    taxonomy => self,
+                    term => term,
+                    pages => paginator.pages,
+                    paginator => paginator,
+                    feed => feed,
+                );
+                let destination_file = term_folder.join(paginator.folder()).join("index.html");
+                generated.push(render(&self.term_template, ctx, &destination_file).await);
+            }
+            if site.feeds.enabled && self.feed {
+                let feed = Feed::new(site, &term.name, &term.name, &route, &pages);
+                generated.extend(feed.generate(site, &term_folder).await);
+            }
+        }
+        generated
+    }
+}
+
+/// A value of a taxonomy, like a tag.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Term {
+    pub name: String,
+    pub slug: String,
+    pub url: String,
+    /// Number of pages classified with this term.
+    pub count: usize,
+}
+
+impl Term {
+    fn new(site: &Site, taxonomy: &Taxonomy, name: &str, count: usize) -> Self {
+        let slug = slugify(name);
+        let url = PathBuf::from(&site.url)
+            .join(taxonomy.prefix())
+            .join(&slug)
+            .to_string_lossy()
+            .to_string();
+        Self {
+            name: name.to_string(),
+            slug,
+            url,
+            count,
+        }
+    }
+}
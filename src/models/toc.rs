@@ -0,0 +1,123 @@
+use comrak::adapters::{HeadingAdapter, HeadingMeta};
+use comrak::nodes::Sourcepos;
+use comrak::Anchorizer;
+use serde::{Serialize, Deserialize};
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// Table of contents of a page, from the `toc` map of its front matter.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Toc {
+    /// Show the table of contents in the page.
+    pub show: bool,
+    /// Deepest level of the headings listed, from 1 to 6.
+    pub depth: u8,
+}
+
+impl Default for Toc {
+    fn default() -> Self {
+        Self {
+            show: false,
+            depth: 3,
+        }
+    }
+}
+
+/// Options of the headings, from the `headings` section of the markdown
+/// options.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Headings {
+    /// Add a link to itself after the text of every heading.
+    pub anchors: bool,
+}
+
+/// A heading of a page with the ones below it, as exposed to templates.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Heading {
+    pub level: u8,
+    pub text: String,
+    pub id: String,
+    pub children: Vec<Heading>,
+}
+
+impl Heading {
+    /// Nest the `headings` of a page, in the order they appear, under the
+    /// previous one with a lower level, leaving out the empty ones and the
+    /// ones deeper than `depth`.
+    pub fn tree(headings: &[Heading], depth: u8) -> Vec<Heading> {
+        let mut tree = Vec::new();
+        for heading in headings.iter().filter(|heading| heading.level <= depth && !heading.text.is_empty()) {
+            insert(&mut tree, heading.clone());
+        }
+        tree
+    }
+}
+
+fn insert(siblings: &mut Vec<Heading>, heading: Heading) {
+    match siblings.last_mut() {
+        Some(last) if last.level < heading.level => insert(&mut last.children, heading),
+        _ => siblings.push(heading),
+    }
+}
+
+/// Renders the headings with an id made from their text, unique in the
+/// page, and collects them for the table of contents.
+pub struct Collector {
+    anchors: bool,
+    anchorizer: Mutex<Anchorizer>,
+    headings: Mutex<Vec<Heading>>,
+}
+
+impl Collector {
+    pub fn new(headings: &Headings) -> Self {
+        Self {
+            anchors: headings.anchors,
+            anchorizer: Mutex::new(Anchorizer::new()),
+            headings: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Headings rendered so far, in the order they appear.
+    pub fn headings(self) -> Vec<Heading> {
+        self.headings.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn last_id(&self) -> String {
+        self.headings.lock().unwrap_or_else(|e| e.into_inner())
+            .last()
+            .map(|heading| heading.id.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl HeadingAdapter for Collector {
+    fn enter(&self, output: &mut dyn Write, heading: &HeadingMeta, sourcepos: Option<Sourcepos>) -> io::Result<()> {
+        let text = heading.content.trim();
+        // headings without letters or numbers, like `!!!`, would get an
+        // empty id
+        let base = if text.chars().any(char::is_alphanumeric) { text } else { "section" };
+        let id = self.anchorizer.lock().unwrap_or_else(|e| e.into_inner())
+            .anchorize(base.to_string());
+        write!(output, "<h{} id=\"{}\"", heading.level, id)?;
+        if let Some(sourcepos) = sourcepos {
+            write!(output, " data-sourcepos=\"{}\"", sourcepos)?;
+        }
+        output.write_all(b">")?;
+        self.headings.lock().unwrap_or_else(|e| e.into_inner()).push(Heading {
+            level: heading.level,
+            text: text.to_string(),
+            id,
+            children: Vec::new(),
+        });
+        Ok(())
+    }
+
+    fn exit(&self, output: &mut dyn Write, heading: &HeadingMeta) -> io::Result<()> {
+        if self.anchors {
+            write!(output, "<a class=\"anchor\" href=\"#{}\" aria-hidden=\"true\">#</a>", self.last_id())?;
+        }
+        writeln!(output, "</h{}>", heading.level)
+    }
+}
@@ -1,22 +1,46 @@
 use std::fmt::{self, Display, Formatter};
 use async_recursion::async_recursion;
-use notify::{event::{CreateKind, RemoveKind}, EventKind};
+use notify::{event::{CreateKind, ModifyKind, RenameMode}, EventKind};
 use tracing::{error, debug};
 use tokio::fs;
-use std::error::Error;
 use std::path::{Path, PathBuf};
 use std::ffi::OsStr;
+use std::collections::{BTreeSet, HashSet};
+use chrono::{DateTime, Utc};
 use super::super::models::Config;
+use super::{links, BuildReport, Manifest};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify, RwLock};
+use once_cell::sync::Lazy;
 
-use super::super::models::{Page, Index, Site};
+use super::super::models::redirect::{self, Redirects};
+use super::super::models::{archive, highlight, BuildError, Defaults, Feed, Page, Index, Section, Site, DEFAULTS_FILE};
+
+/// Redirects of the published site. Taken for writing while a new build
+/// replaces the destination folder, or its redirects change, and for reading
+/// while the server reads from it.
+pub static PUBLISHING: Lazy<RwLock<Redirects>> = Lazy::new(|| RwLock::new(Redirects::default()));
+
+/// Source of the redirects in the manifest.
+const REDIRECTS: &str = "@redirects";
+
+/// Pages dated in the future, sorted by the time they have to be published.
+type Schedule = BTreeSet<(DateTime<Utc>, PathBuf)>;
 
 #[derive(Debug, Clone)]
 pub struct Generator {
     pub site: Site,
     pub origin: PathBuf,
     pub destination: PathBuf,
+    pub assets: PathBuf,
+    pub drafts: bool,
+    pub future: bool,
+    pub paginate: usize,
+    pub redirects: Option<PathBuf>,
+    manifest: Arc<Mutex<Manifest>>,
+    schedule: Arc<Mutex<Schedule>>,
+    /// Notified when a page is scheduled before the next one.
+    rescheduled: Arc<Notify>,
 }
 
 impl Generator {
@@ -26,80 +50,663 @@ impl Generator {
             site: config.site.clone(),
             origin: Path::new(&config.source).to_path_buf(),
             destination: Path::new(&config.destination).to_path_buf(),
+            assets: Path::new(&config.assets).to_path_buf(),
+            drafts: config.drafts,
+            future: config.future,
+            paginate: config.paginate,
+            redirects: config.redirects.as_ref().map(PathBuf::from),
+            manifest: Arc::new(Mutex::new(Manifest::default())),
+            schedule: Arc::new(Mutex::new(BTreeSet::new())),
+            rescheduled: Arc::new(Notify::new()),
         }
     }
 
-    pub async fn replicate_folder(&self, path: &PathBuf) {
-        generate_folder(&self.site, &self.origin, &self.destination, path, false).await;
+    pub async fn replicate_folder(&self, path: &PathBuf) -> BuildReport {
+        let mut report = self.generate_folder(path, true).await;
+        if let Some(parent) = path.parent() {
+            if parent.starts_with(&self.origin) {
+                report.append(self.generate_index(parent).await);
+            }
+        }
+        report
     }
 
-    pub async fn initial_replication(&self) {
+    /// Re-render a single markdown file and the index of the folder that
+    /// contains it. Changes to `index.md` only regenerate the index.
+    pub async fn replicate_file(&self, path: &Path) -> BuildReport {
+        let mut report = BuildReport::default();
+        if is_defaults(path) {
+            // every page below inherits them
+            if let Some(folder) = path.parent() {
+                report.append(self.replicate_folder(&folder.to_path_buf()).await);
+            }
+            return report;
+        }
+        if !is_markdown(path) {
+            debug!("Ignoring {:?}", path);
+            return report;
+        }
+        let Some(folder) = path.parent() else {
+            return report;
+        };
+        if !folder.starts_with(&self.origin) {
+            debug!("{:?} is outside {:?}", path, self.origin);
+            return report;
+        }
+        if !is_index(path) {
+            debug!("Replicating {:?}. From {:?} to {:?}", path, self.origin, self.destination);
+            let defaults = match Defaults::read(&self.origin, folder).await {
+                Ok(defaults) => defaults,
+                Err(e) => {
+                    report.push(e);
+                    return report;
+                },
+            };
+            match self.read_page(folder, path, &defaults).await {
+                Ok(Some(page)) => report.append(self.generate_page(path, &page).await),
+                Ok(None) => self.remove_outputs(path).await,
+                Err(e) => {
+                    report.push(e);
+                    self.remove_outputs(path).await;
+                },
+            }
+        }
+        report.append(self.generate_index(folder).await);
+        report
+    }
+
+    /// Generate the whole site into a staging folder and swap it with the
+    /// destination once it is complete, so the site being served is never
+    /// half rendered. When the build has errors the swap is skipped and the
+    /// current site is kept, unless there is none yet, as with a failed swap.
+    /// The errors are returned either way.
+    pub async fn initial_replication(&self) -> BuildReport {
         debug!("=============================");
-        if let Ok(true) = tokio::fs::try_exists(&self.destination).await{
-            match tokio::fs::remove_dir_all(&self.destination).await {
-                Ok(()) => debug!("Delete main folder"),
-                Err(err) => error!("Can not delete main folder: {}", err),
+        let staging_folder = sibling(&self.destination, "staging");
+        let old_folder = sibling(&self.destination, "old");
+        for folder in [&staging_folder, &old_folder] {
+            if let Ok(true) = fs::try_exists(folder).await {
+                if let Err(err) = fs::remove_dir_all(folder).await {
+                    error!("Can not delete folder {:?}: {}", folder, err);
+                }
             }
         }
-        match tokio::fs::create_dir_all(&self.destination).await {
-            Ok(()) => debug!("Created destination folder {:?}", self.destination),
-            Err(err) => error!("Can not create destination folder {:?}: {}", self.destination, err),
+        match fs::create_dir_all(&staging_folder).await {
+            Ok(()) => debug!("Created staging folder {:?}", staging_folder),
+            Err(err) => {
+                error!("Can not create staging folder {:?}: {}", staging_folder, err);
+                return BuildError::Write { path: staging_folder, cause: err }.into();
+            },
+        }
+
+        let staging = Self {
+            destination: staging_folder.clone(),
+            manifest: Arc::new(Mutex::new(Manifest::default())),
+            ..self.clone()
+        };
+        let mut report = staging.generate_folder(&self.origin, true).await;
+        report.append(staging.generate_listings().await);
+        report.append(staging.collisions().await);
+        report.log();
+        let published = matches!(fs::try_exists(&self.destination).await, Ok(true));
+        if !report.is_empty() {
+            if published {
+                error!("Build finished with {} errors. Keeping the current site", report.len());
+                if let Err(err) = fs::remove_dir_all(&staging_folder).await {
+                    error!("Can not delete folder {:?}: {}", staging_folder, err);
+                }
+                return report;
+            }
+            error!("Build finished with {} errors. Publishing it, as there is no site yet", report.len());
         }
 
-        generate_folder(&self.site, &self.origin, &self.destination,&self.origin, true).await;
+        {
+            // hold the lock while the folders are swapped, so no request
+            // finds the destination missing
+            let mut redirects = PUBLISHING.write().await;
+            if published {
+                if let Err(err) = fs::rename(&self.destination, &old_folder).await {
+                    error!("Can not move {:?} to {:?}: {}", self.destination, old_folder, err);
+                    report.push(BuildError::Write { path: self.destination.clone(), cause: err });
+                    return report;
+                }
+            }
+            if let Err(err) = fs::rename(&staging_folder, &self.destination).await {
+                error!("Can not move {:?} to {:?}: {}", staging_folder, self.destination, err);
+                report.push(BuildError::Write { path: self.destination.clone(), cause: err });
+                if published {
+                    if let Err(err) = fs::rename(&old_folder, &self.destination).await {
+                        error!("Can not restore {:?}: {}", self.destination, err);
+                        report.push(BuildError::Write { path: self.destination.clone(), cause: err });
+                    }
+                }
+                return report;
+            }
+            *redirects = redirect::load(&self.destination).await;
+        }
+        *self.manifest.lock().await = staging.manifest.lock().await.clone();
+        if published {
+            if let Err(err) = fs::remove_dir_all(&old_folder).await {
+                error!("Can not delete old folder {:?}: {}", old_folder, err);
+            }
+        }
         debug!("=============================");
+        report
+    }
+
+    /// Build the whole site into a temporary folder, leaving the destination
+    /// untouched, and report every error of the build, the files generated
+    /// by more than one source, like pages with the same slug, and the
+    /// links to pages or files of the site that do not exist.
+    pub async fn check(&self) -> BuildReport {
+        let folder = std::env::temp_dir().join(format!("markdownweb-check-{}", std::process::id()));
+        // a folder left by a previous process with the same id
+        if fs::try_exists(&folder).await.unwrap_or(false) {
+            if let Err(cause) = fs::remove_dir_all(&folder).await {
+                return BuildError::Write { path: folder, cause }.into();
+            }
+        }
+        if let Err(cause) = fs::create_dir_all(&folder).await {
+            return BuildError::Write { path: folder, cause }.into();
+        }
+        let _cleanup = Cleanup(folder.clone());
+        let checker = Self {
+            destination: folder.clone(),
+            manifest: Arc::new(Mutex::new(Manifest::default())),
+            schedule: Arc::new(Mutex::new(BTreeSet::new())),
+            ..self.clone()
+        };
+        let mut report = checker.generate_folder(&self.origin, true).await;
+        report.append(checker.generate_listings().await);
+        report.append(checker.collisions().await);
+        let manifest = checker.manifest.lock().await.clone();
+        let redirects = redirect::load(&folder).await;
+        let mut entries: Vec<(&PathBuf, &Vec<PathBuf>)> = manifest.entries().collect();
+        entries.sort();
+        // pages go first, so the links of their summaries are reported
+        // against them and not again for every listing that repeats them
+        let (pages, listings): (Vec<_>, Vec<_>) = entries.into_iter()
+            .partition(|(source, _)| manifest.has_page(source));
+        let mut reported = HashSet::new();
+        for (source, outputs) in pages.into_iter().chain(listings) {
+            for output in outputs.iter().filter(|output| output.extension() == Some(OsStr::new("html"))) {
+                // listings have no source file
+                let path = if source.to_string_lossy().starts_with('@') { output } else { source };
+                for (target, e) in checker.check_links(path, output, &redirects).await {
+                    if reported.insert(target) || manifest.has_page(source) {
+                        report.push(e);
+                    }
+                }
+            }
+        }
+        report
+    }
+
+    /// Report the files generated by more than one source, like pages with
+    /// the same permalink, where the last one written wins.
+    async fn collisions(&self) -> BuildReport {
+        let mut report = BuildReport::default();
+        for (output, sources) in self.manifest.lock().await.collisions() {
+            for pair in sources.windows(2) {
+                report.push(BuildError::Duplicate {
+                    path: pair[1].clone(),
+                    output: output.clone(),
+                    other: pair[0].clone(),
+                });
+            }
+        }
+        report
     }
 
+    /// Errors of the internal links of the generated file `output` that lead
+    /// nowhere, with the path inside the site each one goes to.
+    async fn check_links(&self, path: &Path, output: &Path, redirects: &Redirects) -> Vec<(String, BuildError)> {
+        let mut broken = Vec::new();
+        let html = match fs::read_to_string(self.destination.join(output)).await {
+            Ok(html) => html,
+            Err(cause) => return vec![(output.to_string_lossy().to_string(), BuildError::Read { path: self.destination.join(output), cause })],
+        };
+        let mut checked = BTreeSet::new();
+        for link in links::links(&html) {
+            let Some(target) = links::resolve(&link, &self.site.url, output) else {
+                continue;
+            };
+            if !checked.insert(target.clone()) {
+                continue;
+            }
+            let found = match target.strip_prefix("assets/") {
+                Some(asset) => is_file(&self.assets.join(asset)).await,
+                None => {
+                    let file = self.destination.join(&target);
+                    is_file(&file).await
+                        || is_file(&file.join("index.html")).await
+                        || redirects.find(&target).is_some()
+                },
+            };
+            if !found {
+                broken.push((target, BuildError::BrokenLink { path: path.to_path_buf(), link }));
+            }
+        }
+        broken
+    }
 
-    pub async fn replicate(&self, event: notify::Event) -> Result<(), Box<dyn Error>>{
+    /// Update the site after a change in the source, returning the errors
+    /// found.
+    pub async fn replicate(&self, event: notify::Event) -> BuildReport {
+        let mut report = BuildReport::default();
         match event.kind {
             EventKind::Create(create) => {
                 match create {
                     CreateKind::File => {
                         for path in event.paths.iter() {
-                            //self.replicate_file(path).await;
-                            debug!("Replicating {:?}. From {:?} to {:?}", path, self.origin, self.destination);
+                            report.append(self.replicate_file(path).await);
                         }
                     },
                     CreateKind::Folder => {
                         for path in event.paths.iter() {
-                            self.replicate_folder(path).await;
+                            report.append(self.replicate_folder(path).await);
                         }
                     },
-                    _ => {},
+                    _ => return report,
                 }
             }
             EventKind::Modify(modify) => {
-                debug!("Replicating {:?} to {:?}", self.origin, self.destination);
                 debug!("Modify: {:?}", modify);
-            }
-            EventKind::Remove(delete) => {
-                match delete {
-                    RemoveKind::File => {
+                match modify {
+                    ModifyKind::Any | ModifyKind::Data(_) | ModifyKind::Name(RenameMode::To) => {
                         for path in event.paths.iter() {
-                            let destination = self.get_absolute_destination(path);
-                            debug!("Deleting file: {:?}", &destination);
-                            fs::remove_file(&destination).await?
+                            report.append(self.replicate_path(path).await);
                         }
                     },
-                    RemoveKind::Folder => {
+                    ModifyKind::Name(RenameMode::From) => {
                         for path in event.paths.iter() {
-                            let destination = self.get_absolute_destination(path);
-                            debug!("Deleting folder: {:?}", &destination);
-                            fs::remove_dir_all(&destination).await?
+                            report.append(self.remove_source(path).await);
                         }
                     },
-                    _ => {},
+                    ModifyKind::Name(RenameMode::Both) => {
+                        // paths are [from, to]
+                        if let [from, to] = event.paths.as_slice() {
+                            report.append(self.remove_source(from).await);
+                            report.append(self.replicate_path(to).await);
+                        }
+                    },
+                    ModifyKind::Name(_) => {
+                        // the platform does not tell which side of the rename
+                        // this is, so check whether the path is still there
+                        for path in event.paths.iter() {
+                            if let Ok(true) = fs::try_exists(path).await {
+                                report.append(self.replicate_path(path).await);
+                            } else {
+                                report.append(self.remove_source(path).await);
+                            }
+                        }
+                    },
+                    _ => return report,
                 }
             }
-            _ => {}
+            EventKind::Remove(_) => {
+                for path in event.paths.iter() {
+                    report.append(self.remove_source(path).await);
+                }
+            }
+            _ => return report,
+        }
+        report.append(self.generate_listings().await);
+        report.append(self.collisions().await);
+        self.publish_redirects().await;
+        report
+    }
+
+    /// Serve the redirects generated by an update of the published site.
+    async fn publish_redirects(&self) {
+        *PUBLISHING.write().await = redirect::load(&self.destination).await;
+    }
+
+    async fn replicate_path(&self, path: &Path) -> BuildReport {
+        match fs::metadata(path).await {
+            Ok(metadata) if metadata.is_dir() => self.replicate_folder(&path.to_path_buf()).await,
+            Ok(metadata) if metadata.is_file() => self.replicate_file(path).await,
+            _ => {
+                debug!("Nothing to replicate for {:?}", path);
+                BuildReport::default()
+            },
+        }
+    }
+
+    /// Delete everything generated from `path`, that can be a file or a
+    /// folder, and refresh the index of the folder that contained it.
+    async fn remove_source(&self, path: &Path) -> BuildReport {
+        if !path.starts_with(&self.origin) || path == self.origin {
+            return BuildReport::default();
+        }
+        if is_defaults(path) {
+            return match path.parent() {
+                Some(folder) => self.replicate_folder(&folder.to_path_buf()).await,
+                None => BuildReport::default(),
+            };
+        }
+        let outputs = self.manifest.lock().await.remove(path);
+        if outputs.is_empty() && !is_markdown(path) {
+            debug!("Nothing generated from {:?}", path);
+            return BuildReport::default();
+        }
+        debug!("Removing source: {:?}", path);
+        self.delete_outputs(outputs).await;
+        match path.parent() {
+            Some(folder) if matches!(fs::try_exists(folder).await, Ok(true)) => self.generate_index(folder).await,
+            _ => BuildReport::default(),
         }
-    Ok(())
     }
 
-    fn get_absolute_destination(&self, path: &Path) -> PathBuf {
-        let relative = path.strip_prefix(&self.origin).unwrap();
-        self.destination.join(relative)
+    async fn remove_outputs(&self, source: &Path) {
+        let outputs = self.manifest.lock().await.remove(source);
+        self.delete_outputs(outputs).await;
+    }
+
+    /// Delete generated files, and the folders they leave empty.
+    async fn delete_outputs(&self, outputs: Vec<PathBuf>) {
+        for output in outputs {
+            let destination = self.destination.join(&output);
+            debug!("Deleting file: {:?}", &destination);
+            match fs::remove_file(&destination).await {
+                Ok(()) => {},
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+                Err(e) => error!("Can not delete {:?}. {}", &destination, e),
+            }
+            let mut folder = destination.parent();
+            while let Some(current) = folder {
+                if current == self.destination || fs::remove_dir(current).await.is_err() {
+                    break;
+                }
+                debug!("Deleted empty folder: {:?}", current);
+                folder = current.parent();
+            }
+        }
+    }
+
+    /// Record the files generated from `source`, deleting the ones it
+    /// generated before and are not generated anymore (e.g. after a slug
+    /// change), and return the errors of the ones that failed.
+    async fn record(&self, source: &Path, generated: Vec<Result<PathBuf, BuildError>>) -> BuildReport {
+        let mut report = BuildReport::default();
+        let mut outputs = Vec::new();
+        for result in generated {
+            match result {
+                Ok(file) => outputs.extend(file.strip_prefix(&self.destination).ok().map(Path::to_path_buf)),
+                Err(e) => report.push(e),
+            }
+        }
+        let stale = self.manifest.lock().await.insert(source, outputs);
+        self.delete_outputs(stale).await;
+        report
+    }
+
+    async fn generate_page(&self, source: &Path, page: &Page) -> BuildReport {
+        let generated = page.generate(&self.site, &self.destination).await;
+        if generated.is_ok() {
+            self.manifest.lock().await.insert_page(source, page);
+        } else {
+            self.manifest.lock().await.remove_page(source);
+        }
+        self.record(source, vec![generated]).await
+    }
+
+    /// Regenerate the index of `folder` and the ones of its parents, as
+    /// they show its pages and sections too.
+    async fn generate_index(&self, folder: &Path) -> BuildReport {
+        let mut report = BuildReport::default();
+        let mut current = Some(folder);
+        while let Some(folder) = current.filter(|folder| folder.starts_with(&self.origin)) {
+            let pages = self.read_pages(folder).await;
+            report.append(self.write_index(folder, pages).await);
+            current = folder.parent();
+        }
+        report
+    }
+
+    async fn write_index(&self, folder: &Path, pages: Vec<Page>) -> BuildReport {
+        let page_route = self.get_route(folder);
+        let destination_folder = self.destination.join(&page_route);
+        let mut index = match Index::read(&self.site, &page_route, &folder.to_path_buf(), &destination_folder, pages).await {
+            Ok(index) => index,
+            Err(e) => return e.into(),
+        };
+        if index.index.draft && !self.drafts {
+            // its pages are still published, but not the listing of them
+            debug!("Skipping draft index {:?}", folder);
+            self.manifest.lock().await.remove_section(folder);
+            return self.record(folder, Vec::new()).await;
+        }
+        {
+            let mut manifest = self.manifest.lock().await;
+            if index.index.metadata.recursive {
+                index.include_descendants(manifest.pages_below(folder));
+            }
+            index.sections = manifest.sections_of(folder).into_iter()
+                .map(|(child, section)| Section::new(section, manifest.pages_below(&child).len()))
+                .collect();
+            index.sections.sort_by(|a, b| a.index.metadata.weight.cmp(&b.index.metadata.weight)
+                .then(a.index.metadata.title.cmp(&b.index.metadata.title)));
+            manifest.insert_section(folder, &index.index);
+        }
+        let mut generated = index.generate(&self.site, &destination_folder, self.paginate).await;
+        if self.site.feeds.enabled && !page_route.as_os_str().is_empty() {
+            let metadata = &index.index.metadata;
+            let feed = Feed::new(&self.site, &metadata.title, &metadata.excerpt, &page_route, &index.pages);
+            generated.extend(feed.generate(&self.site, &destination_folder).await);
+        }
+        if let Some(podcast) = self.site.podcast.as_ref().filter(|podcast| Path::new(&podcast.section) == page_route) {
+            let feed = Feed::podcast(&self.site, podcast, &page_route, &index.pages);
+            generated.push(feed.render(&self.site, "podcast.xml", &destination_folder).await);
+        }
+        self.record(folder, generated).await
+    }
+
+    /// Generate the pages and the index of a folder, returning the errors
+    /// of the ones that failed.
+    #[async_recursion]
+    pub async fn generate_folder(&self, path: &PathBuf, recursive: bool) -> BuildReport {
+        debug!("Source folder: {:?}", path);
+        let page_route = self.get_route(path);
+        debug!("Route: {:?}", page_route);
+        let mut report = BuildReport::default();
+        // the pages are not generated with wrong values, and the error is
+        // reported once, by the folder of the file
+        let defaults = match Defaults::read(&self.origin, path).await {
+            Ok(defaults) => defaults,
+            Err(e) => {
+                if e.path().parent() == Some(path.as_path()) {
+                    report.push(e);
+                }
+                return report;
+            },
+        };
+        let mut pages = Vec::new();
+        match fs::read_dir(path).await {
+            Ok(mut entries) => {
+                while let Ok(Some(entry)) = entries.next_entry().await {
+                    let entry_path = entry.path();
+                    let file_type = match entry.file_type().await {
+                        Ok(file_type) => file_type,
+                        Err(cause) => {
+                            report.push(BuildError::Read { path: entry_path, cause });
+                            continue;
+                        },
+                    };
+                    if file_type.is_dir() {
+                        if recursive {
+                            report.append(self.generate_folder(&entry_path, recursive).await);
+                        }
+                    }else if file_type.is_file() &&
+                            is_markdown(&entry_path) &&
+                            !is_index(&entry_path){
+                        debug!("File: {:?}", entry_path);
+                        match Page::read(&self.site, &page_route, &entry_path, &defaults).await {
+                            Ok(page) => {
+                                if !self.is_visible(&entry_path, &page).await {
+                                    debug!("Skipping {:?}", entry_path);
+                                    self.remove_outputs(&entry_path).await;
+                                    continue;
+                                }
+                                // only the pages written are listed
+                                let generated = self.generate_page(&entry_path, &page).await;
+                                if generated.is_empty() {
+                                    pages.push(page);
+                                }
+                                report.append(generated);
+                            },
+                            Err(e) => {
+                                report.push(e);
+                                self.remove_outputs(&entry_path).await;
+                            },
+                        }
+                    }
+                }
+                report.append(self.write_index(path, pages).await);
+            },
+            Err(cause) => report.push(BuildError::Read { path: path.clone(), cause }),
+        }
+        report
+    }
+
+    /// Generate the listings that depend on every page of the site.
+    pub async fn generate_listings(&self) -> BuildReport {
+        let pages = self.manifest.lock().await.pages();
+        let mut report = BuildReport::default();
+        let generated = if self.site.feeds.enabled {
+            let feed = Feed::new(&self.site, &self.site.title, &self.site.description, Path::new(""), &pages);
+            feed.generate(&self.site, &self.destination).await
+        } else {
+            Vec::new()
+        };
+        report.append(self.record(Path::new("@feeds"), generated).await);
+        for taxonomy in self.site.taxonomies.iter() {
+            let generated = taxonomy.generate(&self.site, &self.destination, &pages, self.paginate).await;
+            report.append(self.record(Path::new(&format!("@{}", taxonomy.name)), generated).await);
+        }
+        let generated = highlight::generate(&self.site.markdown.highlight, &self.destination).await;
+        report.append(self.record(Path::new("@highlight"), generated).await);
+        let generated = if self.site.archive {
+            archive::generate(&self.site, &self.destination, &pages).await
+        } else {
+            Vec::new()
+        };
+        report.append(self.record(Path::new("@archive"), generated).await);
+        let file = match &self.redirects {
+            Some(path) => redirect::read(path).await.unwrap_or_else(|e| {
+                report.push(e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        let file_path = self.redirects.clone().unwrap_or_default();
+        let outputs: HashSet<PathBuf> = self.manifest.lock().await.entries()
+            .filter(|(source, _)| source.as_path() != Path::new(REDIRECTS))
+            .flat_map(|(_, outputs)| outputs.iter().cloned())
+            .collect();
+        let (redirects, errors) = redirect::collect(&self.site, &pages, &outputs, file, &file_path);
+        for e in errors {
+            report.push(e);
+        }
+        let generated = redirect::generate(&self.site, &self.destination, &redirects).await;
+        report.append(self.record(Path::new(REDIRECTS), generated).await);
+        report
+    }
+
+    /// Drafts are only visible when previewing them, and pages dated in the
+    /// future are withheld and scheduled until their date arrives.
+    async fn is_visible(&self, source: &Path, page: &Page) -> bool {
+        if page.draft && !self.drafts {
+            return false;
+        }
+        let date = page.metadata.date.with_timezone(&Utc);
+        if date > Utc::now() && !self.future {
+            debug!("Scheduling {:?} for {}", source, date);
+            let mut schedule = self.schedule.lock().await;
+            let earliest = schedule.first().is_none_or(|(first, _)| date < *first);
+            schedule.insert((date, source.to_path_buf()));
+            if earliest {
+                self.rescheduled.notify_one();
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Read a page, that is `None` when it is not visible.
+    async fn read_page(&self, folder: &Path, path: &Path, defaults: &Defaults) -> Result<Option<Page>, BuildError> {
+        let page = Page::read(&self.site, &self.get_route(folder), &path.to_path_buf(), defaults).await?;
+        if self.is_visible(path, &page).await {
+            Ok(Some(page))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Next time a scheduled page has to be published.
+    pub async fn next_scheduled(&self) -> Option<DateTime<Utc>> {
+        self.schedule.lock().await.first().map(|(date, _)| *date)
+    }
+
+    /// Wait until a page is scheduled before the next one.
+    pub async fn rescheduled(&self) {
+        self.rescheduled.notified().await
+    }
+
+    /// Generate the scheduled pages whose date has arrived, along with the
+    /// index of their folders.
+    pub async fn publish_scheduled(&self) -> BuildReport {
+        let now = Utc::now();
+        let due: Vec<PathBuf> = {
+            let mut schedule = self.schedule.lock().await;
+            let mut due = Vec::new();
+            while schedule.first().is_some_and(|(date, _)| *date <= now) {
+                if let Some((_, source)) = schedule.pop_first() {
+                    due.push(source);
+                }
+            }
+            due
+        };
+        let mut report = BuildReport::default();
+        if due.is_empty() {
+            return report;
+        }
+        for source in due {
+            debug!("Publishing scheduled page: {:?}", source);
+            if let Ok(true) = fs::try_exists(&source).await {
+                report.append(self.replicate_file(&source).await);
+            }
+        }
+        report.append(self.generate_listings().await);
+        self.publish_redirects().await;
+        report
+    }
+
+    /// Read every visible page in `folder` without rendering them. The
+    /// pages that can not be read or were not written are left out, as
+    /// their errors are reported when they are generated.
+    async fn read_pages(&self, folder: &Path) -> Vec<Page> {
+        let Ok(defaults) = Defaults::read(&self.origin, folder).await else {
+            return Vec::new();
+        };
+        let mut pages = Vec::new();
+        if let Ok(mut entries) = fs::read_dir(folder).await{
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let entry_path = entry.path();
+                if entry_path.is_file() && is_markdown(&entry_path) && !is_index(&entry_path) {
+                    if let Ok(Some(page)) = self.read_page(folder, &entry_path, &defaults).await {
+                        if self.manifest.lock().await.has_page(&entry_path) {
+                            pages.push(page);
+                        }
+                    }
+                }
+            }
+        }
+        pages
+    }
+
+    fn get_route(&self, folder: &Path) -> PathBuf {
+        folder.strip_prefix(&self.origin).unwrap().to_path_buf()
     }
 }
 
@@ -109,35 +716,36 @@ impl Display for Generator {
     }
 }
 
-#[async_recursion]
-pub async fn generate_folder(site: &Site, main_source: &PathBuf, main_destination: &PathBuf, path: &PathBuf, recursive: bool) {
-    debug!("Source folder: {:?}", path);
-    let page_route = path.strip_prefix(main_source).unwrap().to_path_buf();
-    debug!("Route: {:?}", page_route);
-    let destination_folder = main_destination.join(&page_route);
-    debug!("Destination folder: {:?}", &destination_folder);
-    let mut directories = Vec::new();
-    let mut pages = Vec::new();
-    if let Ok(mut entries) = fs::read_dir(path).await{
-        while let Ok(Some(entry)) = entries.next_entry().await {
-            if entry.file_type().await.unwrap().is_dir() {
-                directories.push(entry.path());
-                if recursive {
-                    generate_folder(site, main_source, main_destination, &entry.path(), recursive).await;
-                }
-            }else if entry.file_type().await.unwrap().is_file() &&
-                    entry.path().extension().unwrap() == "md" &&
-                    entry.path().file_name() != Some(OsStr::new("index.md")){
-                debug!("File: {:?}", entry.path());
-                let wraped_page = Page::read(&page_route, &entry.path().to_path_buf()).await;
-                if wraped_page.is_some() {
-                    let page = wraped_page.unwrap();
-                    page.generate(site, &destination_folder.to_path_buf()).await;
-                    pages.push(page);
-                }
-            }
+/// Path next to `path` used while building, e.g. `.public.staging`.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+async fn is_file(path: &Path) -> bool {
+    fs::metadata(path).await.is_ok_and(|metadata| metadata.is_file())
+}
+
+fn is_defaults(path: &Path) -> bool {
+    path.file_name() == Some(OsStr::new(DEFAULTS_FILE))
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new("md"))
+}
+
+fn is_index(path: &Path) -> bool {
+    path.file_name() == Some(OsStr::new("index.md"))
+}
+
+/// Deletes the folder when dropped, so the build made by `check` goes away
+/// however it ends, even on a panic.
+struct Cleanup(PathBuf);
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_dir_all(&self.0) {
+            error!("Can not delete folder {:?}: {}", self.0, err);
         }
-        let index = Index::read(&page_route, path, &destination_folder, pages).await.unwrap();
-        index.generate(site, &destination_folder).await;
     }
 }
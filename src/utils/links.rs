@@ -0,0 +1,104 @@
+use std::path::Path;
+
+/// Values of the `href` and `src` attributes of an html document, with
+/// their entities decoded.
+pub fn links(html: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    for attribute in ["href=\"", "src=\""] {
+        let mut rest = html;
+        while let Some(start) = rest.find(attribute) {
+            rest = &rest[start + attribute.len()..];
+            let Some(end) = rest.find('"') else {
+                break;
+            };
+            // skip attributes like `data-src`
+            let preceding = html.len() - rest.len() - attribute.len();
+            let standalone = preceding == 0 || html[..preceding].ends_with(|c: char| c.is_whitespace());
+            if standalone {
+                links.push(unescape(&rest[..end]));
+            }
+            rest = &rest[end..];
+        }
+    }
+    links
+}
+
+/// Path inside the site that `link` goes to, without leading slash, or
+/// `None` when it goes outside of the site or to the same page. `base_url`
+/// is the url of the site and `output` the generated file, relative to the
+/// destination, the link is in.
+pub fn resolve(link: &str, base_url: &str, output: &Path) -> Option<String> {
+    let link = link.split(['#', '?']).next().unwrap_or_default();
+    if link.is_empty() {
+        return None;
+    }
+    let (origin, base_path) = match base_url.find("://") {
+        Some(scheme) => {
+            let host_end = base_url[scheme + 3..].find('/').map(|i| i + scheme + 3).unwrap_or(base_url.len());
+            (&base_url[..host_end], &base_url[host_end..])
+        },
+        None => ("", base_url),
+    };
+    let link = match link.strip_prefix(origin).filter(|_| !origin.is_empty()) {
+        Some(path) if path.is_empty() || path.starts_with('/') => path,
+        _ => link,
+    };
+    let is_external = link.starts_with("//") || link.split('/').next().is_some_and(|segment| segment.contains(':'));
+    if is_external {
+        return None;
+    }
+    let path = if link.starts_with('/') || link.is_empty() {
+        let base_path = base_path.trim_end_matches('/');
+        link.strip_prefix(base_path).unwrap_or(link).to_string()
+    } else {
+        let folder = output.parent().unwrap_or(Path::new("")).to_string_lossy().to_string();
+        format!("{}/{}", folder, link)
+    };
+    let path = decode(&path);
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {},
+            ".." => {
+                segments.pop();
+            },
+            segment => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// Decode the `%XX` escapes of a url path, like `caf%C3%A9`, into the name
+/// of the file.
+fn decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn unescape(value: &str) -> String {
+    value.replace("&#x2f;", "/")
+        .replace("&#x27;", "'")
+        .replace("&#39;", "'")
+        .replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
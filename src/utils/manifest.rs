@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use super::super::models::Page;
+
+/// Keeps track of the files generated from every source, so they can be
+/// cleaned up when the source is removed, renamed or changes its slug.
+///
+/// Sources are absolute paths inside the origin folder (a folder is the
+/// source of its index), or the name of a site-wide listing like `@feeds`,
+/// and outputs are relative to the destination folder. It also keeps the
+/// pages and the indexes that have been generated, to build the site-wide
+/// listings and the section trees.
+#[derive(Debug, Default, Clone)]
+pub struct Manifest {
+    entries: HashMap<PathBuf, Vec<PathBuf>>,
+    pages: HashMap<PathBuf, Page>,
+    sections: HashMap<PathBuf, Page>,
+}
+
+impl Manifest {
+    /// Set the outputs of `source` and return the outputs it had before
+    /// that are not produced anymore, by it or by any other source.
+    pub fn insert(&mut self, source: &Path, outputs: Vec<PathBuf>) -> Vec<PathBuf> {
+        let previous = self.entries.insert(source.to_path_buf(), outputs.clone())
+            .unwrap_or_default();
+        previous.into_iter()
+            .filter(|output| !outputs.contains(output) && !self.is_generated(output))
+            .collect()
+    }
+
+    pub fn insert_page(&mut self, source: &Path, page: &Page) {
+        self.pages.insert(source.to_path_buf(), page.clone());
+    }
+
+    /// Whether the page of `source` was written.
+    pub fn has_page(&self, source: &Path) -> bool {
+        self.pages.contains_key(source)
+    }
+
+    pub fn remove_page(&mut self, source: &Path) {
+        self.pages.remove(source);
+    }
+
+    pub fn insert_section(&mut self, folder: &Path, index: &Page) {
+        self.sections.insert(folder.to_path_buf(), index.clone());
+    }
+
+    pub fn remove_section(&mut self, folder: &Path) {
+        self.sections.remove(folder);
+    }
+
+    /// Generated pages in `folder` and its subfolders.
+    pub fn pages_below(&self, folder: &Path) -> Vec<Page> {
+        self.pages.iter()
+            .filter(|(source, _)| source.starts_with(folder))
+            .map(|(_, page)| page.clone())
+            .collect()
+    }
+
+    /// Indexes of the direct subfolders of `folder`.
+    pub fn sections_of(&self, folder: &Path) -> Vec<(PathBuf, Page)> {
+        self.sections.iter()
+            .filter(|(source, _)| source.parent() == Some(folder))
+            .map(|(source, index)| (source.clone(), index.clone()))
+            .collect()
+    }
+
+    /// Every generated page.
+    pub fn pages(&self) -> Vec<Page> {
+        self.pages.values().cloned().collect()
+    }
+
+    /// Outputs of every source.
+    pub fn entries(&self) -> impl Iterator<Item = (&PathBuf, &Vec<PathBuf>)> {
+        self.entries.iter()
+    }
+
+    /// Outputs generated by more than one source, with their sources.
+    pub fn collisions(&self) -> Vec<(PathBuf, Vec<PathBuf>)> {
+        let mut sources: HashMap<&PathBuf, Vec<PathBuf>> = HashMap::new();
+        for (source, outputs) in self.entries.iter() {
+            for output in outputs {
+                sources.entry(output).or_default().push(source.clone());
+            }
+        }
+        sources.into_iter()
+            .filter(|(_, sources)| sources.len() > 1)
+            .map(|(output, mut sources)| {
+                sources.sort();
+                (output.clone(), sources)
+            })
+            .collect()
+    }
+
+    /// Forget `source` and everything below it, returning their outputs
+    /// that no other source generates.
+    pub fn remove(&mut self, source: &Path) -> Vec<PathBuf> {
+        self.pages.retain(|key, _| !key.starts_with(source));
+        self.sections.retain(|key, _| !key.starts_with(source));
+        let sources: Vec<PathBuf> = self.entries.keys()
+            .filter(|key| key.starts_with(source))
+            .cloned()
+            .collect();
+        let outputs: Vec<PathBuf> = sources.iter()
+            .filter_map(|key| self.entries.remove(key))
+            .flatten()
+            .collect();
+        outputs.into_iter()
+            .filter(|output| !self.is_generated(output))
+            .collect()
+    }
+
+    /// Whether some source generates `output`.
+    fn is_generated(&self, output: &Path) -> bool {
+        self.entries.values().any(|outputs| outputs.iter().any(|other| other == output))
+    }
+}
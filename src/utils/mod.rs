@@ -1,3 +1,8 @@
 mod generator;
+mod links;
+mod manifest;
+mod report;
 
-pub use generator::Generator;
+pub use generator::{Generator, PUBLISHING};
+pub use manifest::Manifest;
+pub use report::BuildReport;
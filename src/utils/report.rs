@@ -0,0 +1,40 @@
+use tracing::error;
+use super::super::models::BuildError;
+
+/// Errors found while building the site, or the part of it that changed,
+/// so one bad file never stops the rest of the build.
+#[derive(Debug, Default)]
+pub struct BuildReport {
+    pub errors: Vec<BuildError>,
+}
+
+impl BuildReport {
+    pub fn push(&mut self, error: BuildError) {
+        self.errors.push(error);
+    }
+
+    pub fn append(&mut self, other: BuildReport) {
+        self.errors.extend(other.errors);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Log every error, that already tells its cause.
+    pub fn log(&self) {
+        for err in self.errors.iter() {
+            error!("{}", err);
+        }
+    }
+}
+
+impl From<BuildError> for BuildReport {
+    fn from(error: BuildError) -> Self {
+        Self { errors: vec![error] }
+    }
+}